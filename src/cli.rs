@@ -1,17 +1,82 @@
-use std::path::PathBuf;
+use std::{env, io, path::PathBuf};
 
-use clap::{Arg, ArgAction, ArgMatches, Command, arg, command, value_parser};
+use clap::{
+    Arg, ArgAction, ArgGroup, ArgMatches, Command, arg, command, parser::ValueSource, value_parser,
+};
 
-use crate::sorting::SortingMode;
+use clap_complete::{ArgValueCandidates, CompletionCandidate, env::Shells};
+use clap_mangen::Man;
+
+use crate::{
+    config::Config, hash::HashAlgorithm, quoting::QuotingStyle, sorting::SortingMode,
+    style::ColorMode,
+};
+
+const SHELLS: [&str; 5] = ["bash", "zsh", "fish", "elvish", "powershell"];
 
 pub fn get_cli() -> Command {
     let long = command!("long").about("Print the long format");
     let tree = command!("tree").about("Print the tree format");
     let table = command!("table").about("Print the table format");
-    let config = command!("config").about("Write the default config to $HOME/.config/lils.toml");
+    let config = command!("config")
+        .about("Inspect and change the configuration")
+        .subcommand_required(true)
+        .subcommands([
+            command!("path").about("Print the config files which apply"),
+            command!("show").about("Print the effective config and where each value is set"),
+            command!("get")
+                .about("Print a config value")
+                .arg(arg!(<key> "Dotted config key, e.g. display.icons")),
+            command!("set")
+                .about("Set a value in the user config")
+                .arg(arg!(<key> "Dotted config key, e.g. display.icons"))
+                .arg(arg!(<value> "New value")),
+            command!("edit").about("Open the user config in $EDITOR"),
+            command!("init")
+                .about("Write the default config")
+                .arg(arg!(-f --force "Overwrite an existing config")),
+            command!("check").about("Validate the config files without listing anything"),
+        ]);
+
+    let completions = command!("completions")
+        .about("Print the shell completion script")
+        .long_about(
+            "Print the shell completion script. For example, add `source <(lils completions bash)` \
+             to your .bashrc",
+        )
+        .arg(arg!(<shell> "Shell to complete for").value_parser(SHELLS));
+    let diff = command!("diff")
+        .about("Compare the contents of two directories")
+        .arg(arg!(<left> "Directory to compare from").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(<right> "Directory to compare to").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--identical "Also list entries which are the same on both sides"))
+        .arg(arg!(--json "Print the differences as JSON"));
+    let snapshot = command!("snapshot")
+        .about("Save a listing and compare the directory against it later")
+        .subcommand_required(true)
+        .subcommands([
+            command!("save")
+                .about("Save the listing, with -r for the whole tree")
+                .arg(arg!(<file> "File to save to").value_parser(value_parser!(PathBuf))),
+            command!("compare")
+                .about("Show what was added, removed or modified since a snapshot was saved")
+                .arg(arg!(<file> "Snapshot to compare to").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(--identical "Also list entries which haven't changed"))
+                .arg(arg!(--json "Print the differences as JSON")),
+        ]);
+    let manpage = command!("manpage").about("Print the man page in roff format");
 
     command!()
-        .subcommands([long, tree, table, config])
+        .subcommands([
+            long,
+            tree,
+            table,
+            diff,
+            snapshot,
+            config,
+            completions,
+            manpage,
+        ])
         .arg(
             arg!([path] "Path to directories")
                 .value_parser(value_parser!(PathBuf))
@@ -19,6 +84,23 @@ pub fn get_cli() -> Command {
                 .num_args(1..)
                 .global(true),
         )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .help("Read the config from this file")
+                .value_name("file")
+                .value_parser(value_parser!(PathBuf))
+                .global(true),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .short('p')
+                .help("Apply a profile from the config")
+                .value_name("name")
+                .add(ArgValueCandidates::new(profile_candidates))
+                .global(true),
+        )
         .arg(
             Arg::new("all")
                 .short('a')
@@ -26,6 +108,7 @@ pub fn get_cli() -> Command {
                 .action(ArgAction::SetTrue)
                 .help("Display hidden files"),
         )
+        .arg(negation("no-all", "all"))
         .arg(
             Arg::new("git")
                 .short('g')
@@ -33,6 +116,7 @@ pub fn get_cli() -> Command {
                 .help("Respect .gitignore files")
                 .action(ArgAction::SetTrue),
         )
+        .arg(negation("no-git", "git"))
         .arg(
             Arg::new("icons")
                 .short('i')
@@ -40,6 +124,7 @@ pub fn get_cli() -> Command {
                 .help("Use Nerd Font icons")
                 .action(ArgAction::SetTrue),
         )
+        .arg(negation("no-icons", "icons"))
         .arg(
             Arg::new("no-suffix")
                 .short('n')
@@ -47,6 +132,117 @@ pub fn get_cli() -> Command {
                 .help("Disable filename suffixes")
                 .action(ArgAction::SetTrue),
         )
+        .arg(negation("suffix", "no-suffix").help("Enable filename suffixes"))
+        .arg(
+            Arg::new("summary")
+                .long("summary")
+                .help("Print counts and total size after each listing")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(negation("no-summary", "summary"))
+        .arg(
+            Arg::new("prune")
+                .long("prune")
+                .help("Leave directories with nothing listed in them out of the tree")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(negation("no-prune", "prune").global(true))
+        .arg(
+            Arg::new("max-per-dir")
+                .long("max-per-dir")
+                .help("Limit the entries shown for each directory of the tree, 0 for no limit")
+                .value_name("count")
+                .value_parser(value_parser!(usize))
+                .global(true),
+        )
+        .arg(
+            Arg::new("lines")
+                .long("lines")
+                .help("Show the number of lines in each text file in the long format")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(negation("no-lines", "lines").global(true))
+        .arg(
+            Arg::new("media")
+                .long("media")
+                .help("Show image dimensions and audio or video durations in the long format")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(negation("no-media", "media").global(true))
+        .arg(
+            Arg::new("hash")
+                .long("hash")
+                .help("Show a hash of each file's contents in the long format")
+                .value_name("algorithm")
+                .value_parser(value_parser!(HashAlgorithm))
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("xxh3")
+                .global(true),
+        )
+        .arg(
+            Arg::new("hash-width")
+                .long("hash-width")
+                .help("Shorten hashes to this many characters, 0 for the whole hash")
+                .value_name("chars")
+                .value_parser(value_parser!(usize))
+                .global(true),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .help("Keep the listing on screen, redrawing it when entries change")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("hyperlink")
+                .long("hyperlink")
+                .help("Make file names clickable links in supporting terminals")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(negation("no-hyperlink", "hyperlink"))
+        .arg(
+            Arg::new("oneline")
+                .short('1')
+                .long("oneline")
+                .help("List one entry per line")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("across"),
+        )
+        .arg(negation("no-oneline", "oneline"))
+        .arg(
+            Arg::new("width")
+                .short('w')
+                .long("width")
+                .help("Set the output width instead of detecting it")
+                .value_name("cols")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("across")
+                .short('x')
+                .long("across")
+                .help("List entries across rows instead of down columns")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(negation("no-across", "across"))
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .help("Set when to use coloured output")
+                .value_name("when")
+                .value_parser(value_parser!(ColorMode)),
+        )
+        .arg(
+            Arg::new("quoting-style")
+                .long("quoting-style")
+                .help("Set how file names are quoted and escaped")
+                .value_name("style")
+                .value_parser(value_parser!(QuotingStyle)),
+        )
         .arg(
             Arg::new("recurse")
                 .short('r')
@@ -54,13 +250,51 @@ pub fn get_cli() -> Command {
                 .action(ArgAction::SetTrue)
                 .help("Recurse into subdirectories"),
         )
+        .arg(
+            Arg::new("flat")
+                .long("flat")
+                .action(ArgAction::SetTrue)
+                .help("Recurse and list the whole tree as one list of relative paths")
+                .conflicts_with("recurse"),
+        )
+        .group(ArgGroup::new("recursive").args(["recurse", "flat"]))
+        .arg(
+            Arg::new("absolute")
+                .long("absolute")
+                .help("Show absolute paths in directory headers")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(negation("no-absolute", "absolute"))
+        .arg(
+            Arg::new("dereference")
+                .short('L')
+                .long("dereference")
+                .help("Follow all symlinks")
+                .action(ArgAction::SetTrue)
+                .overrides_with_all(["dereference-args", "no-dereference"]),
+        )
+        .arg(
+            Arg::new("dereference-args")
+                .short('H')
+                .long("dereference-args")
+                .help("Follow symlinks given as paths, but not those inside directories")
+                .action(ArgAction::SetTrue)
+                .overrides_with_all(["dereference", "no-dereference"]),
+        )
+        .arg(negation("no-dereference", "dereference").help("Don't follow any symlinks"))
+        .arg(
+            Arg::new("one-file-system")
+                .long("one-file-system")
+                .help("Don't recurse into directories on other filesystems")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(negation("no-one-file-system", "one-file-system"))
         .arg(
             Arg::new("depth")
                 .short('d')
                 .long("depth")
-                .help("Depth to recurse to")
+                .help("Depth to recurse to, with --recurse, --flat or in the tree")
                 .value_parser(value_parser!(usize))
-                .requires("recurse")
                 .default_value("0"),
         )
         .arg(
@@ -80,6 +314,7 @@ pub fn get_cli() -> Command {
                 .help("Reverse sorted files")
                 .action(ArgAction::SetTrue),
         )
+        .arg(negation("no-reverse", "reverse"))
         .arg(
             Arg::new("mod")
                 .long("mod")
@@ -106,6 +341,41 @@ pub fn get_cli() -> Command {
         )
 }
 
+// Profiles from the config which applies to the current directory
+fn profile_candidates() -> Vec<CompletionCandidate> {
+    let dir = env::current_dir().unwrap_or_default();
+    Config::profile_names(&dir)
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Writes the script which registers `lils` for dynamic completion with the given shell
+pub fn write_completions(shell: &str) -> io::Result<()> {
+    let cli = get_cli();
+    let name = cli.get_name();
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(shell)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unsupported shell"))?;
+
+    completer.write_registration("COMPLETE", name, name, name, &mut io::stdout())
+}
+
+pub fn write_manpage() -> io::Result<()> {
+    Man::new(get_cli()).render(&mut io::stdout())
+}
+
+// `--no-<flag>` to turn off a flag which may have been switched on in the config. Whichever of
+// the two is given last wins.
+fn negation(id: &'static str, flag: &'static str) -> Arg {
+    Arg::new(id)
+        .long(id)
+        .help(format!("Turn off --{flag}"))
+        .action(ArgAction::SetTrue)
+        .overrides_with(flag)
+}
+
 pub fn get_sorting_mode(matches: &ArgMatches) -> Option<SortingMode> {
     if let Some(true) = get_bool(matches, "mod") {
         return Some(SortingMode::Time);
@@ -117,9 +387,30 @@ pub fn get_sorting_mode(matches: &ArgMatches) -> Option<SortingMode> {
         return Some(SortingMode::None);
     }
 
-    matches.get_one::<SortingMode>("sort").copied()
+    get_explicit::<SortingMode>(matches, "sort")
 }
 
+/// A flag's value, only if it was given on the command line, so that config values aren't
+/// overwritten by defaults
 pub fn get_bool(matches: &ArgMatches, id: &str) -> Option<bool> {
-    matches.get_one::<bool>(id).copied()
+    get_explicit(matches, id)
+}
+
+/// Whether a flag or its `--no-` negation was given, whichever came last
+pub fn get_flag(matches: &ArgMatches, id: &str, negation: &str) -> Option<bool> {
+    if let Some(true) = get_bool(matches, id) {
+        Some(true)
+    } else if let Some(true) = get_bool(matches, negation) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+pub fn get_explicit<T: Clone + Send + Sync + 'static>(matches: &ArgMatches, id: &str) -> Option<T> {
+    if matches.value_source(id) == Some(ValueSource::CommandLine) {
+        matches.get_one::<T>(id).cloned()
+    } else {
+        None
+    }
 }
@@ -1,110 +1,481 @@
-use std::{env, fs, io, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashSet},
+    env, fmt, fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
 
-use clap::ArgMatches;
+use clap::{ArgMatches, ValueEnum};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    cli::{get_bool, get_sorting_mode},
+    cli::{get_bool, get_explicit, get_flag, get_sorting_mode},
+    files::Dereference,
+    hash::HashAlgorithm,
+    output::{GridDirection, OutputMode},
+    quoting::QuotingStyle,
     sorting::SortingMode,
+    style::ColorMode,
 };
 
-const CONFIG_PATH: &str = ".config/lils.toml";
+pub mod command;
+mod environment;
+mod validate;
+
+pub use environment::env_profile;
+
+pub use validate::{Diagnostic, Severity, validate};
+
+const CONFIG_FILE: &str = "lils.toml";
+const PROJECT_CONFIG_FILE: &str = ".lils.toml";
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SortingConfig {
     pub mode: SortingMode,
     pub reverse: bool,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Display {
+    pub mode: OutputMode,
     pub icons: bool,
     pub suffix: bool,
+    pub grid: GridDirection,
+    pub quoting: QuotingStyle,
+    pub color: ColorMode,
+    pub hyperlinks: bool,
+    pub oneline: bool,
+    pub absolute_paths: bool,
+    pub summary: bool,
+    pub prune: bool,
+    pub max_per_dir: usize,
+    pub hash: HashAlgorithm,
+    pub hash_width: usize,
+    pub lines: bool,
+    pub media: bool,
+    #[serde(skip)]
+    pub width: Option<usize>,
+    #[serde(skip)]
+    pub tty: bool,
+    #[serde(skip)]
+    pub color_enabled: bool,
+    /// Paths to highlight as recently changed, while watching
+    #[serde(skip)]
+    pub highlighted: HashSet<PathBuf>,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self {
+            mode: OutputMode::default(),
+            icons: false,
+            suffix: true,
+            grid: GridDirection::default(),
+            quoting: QuotingStyle::default(),
+            color: ColorMode::default(),
+            hyperlinks: false,
+            oneline: false,
+            absolute_paths: false,
+            summary: false,
+            prune: false,
+            max_per_dir: 0,
+            hash: HashAlgorithm::default(),
+            hash_width: 12,
+            lines: false,
+            media: false,
+            width: None,
+            tty: false,
+            color_enabled: false,
+            highlighted: HashSet::new(),
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Filter {
     #[serde(skip)]
     pub recurse: bool,
+    #[serde(skip)]
+    pub flat: bool,
     pub depth: usize,
     pub git: bool,
     pub hidden: bool,
+    pub dereference: Dereference,
+    pub one_file_system: bool,
+}
+
+/// Where a config value was set
+#[derive(Debug, Clone)]
+pub enum Source {
+    Default,
+    File(PathBuf),
+    Env(String),
+    Profile(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Env(var) => write!(f, "${var}"),
+            Self::Profile(name) => write!(f, "profile {name}"),
+        }
+    }
+}
+
+/// Settings which can be overlaid onto the config by name with `--profile`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub sorting: SortingConfig,
+    pub display: Display,
+    pub filter: Filter,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub sorting: SortingConfig,
     pub display: Display,
     pub filter: Filter,
+    // Only kept so that profiles are validated, they are applied to the raw tables in `read`
+    #[serde(skip_serializing)]
+    pub profiles: BTreeMap<String, Profile>,
 }
 
 impl Config {
-    fn get_path() -> PathBuf {
-        let mut path = env::home_dir().unwrap();
-        path.push(CONFIG_PATH);
-        path
+    /// The user config file: `--config`, then `$LILS_CONFIG`, then `$XDG_CONFIG_HOME/lils.toml`
+    /// falling back to `$HOME/.config/lils.toml`
+    pub fn get_path(explicit: Option<&Path>) -> Option<PathBuf> {
+        if let Some(path) = explicit {
+            return Some(path.to_path_buf());
+        }
+        if let Some(path) = Self::env_path() {
+            return Some(path);
+        }
+
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::home_dir().map(|h| h.join(".config")))?;
+        Some(config_dir.join(CONFIG_FILE))
     }
 
-    pub fn read() -> io::Result<Self> {
-        let path = Self::get_path();
+    // An empty LILS_CONFIG is treated as unset
+    fn env_path() -> Option<PathBuf> {
+        env::var_os("LILS_CONFIG")
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+    }
 
-        if fs::exists(&path)? {
-            let config_str = fs::read_to_string(path)?;
+    // Project config files from the outermost ancestor of `dir` down to `dir` itself, so that
+    // the closest one is applied last
+    fn project_paths(dir: &Path) -> Vec<PathBuf> {
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let mut paths: Vec<PathBuf> = dir
+            .ancestors()
+            .map(|d| d.join(PROJECT_CONFIG_FILE))
+            .filter(|p| p.is_file())
+            .collect();
+        paths.reverse();
+        paths
+    }
 
-            let de = toml::Deserializer::parse(&config_str).map_err(|_| {
-                io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "toml configuration could not be parsed",
-                )
-            })?;
+    /// Validates every config file which applies to `dir` without merging them
+    pub fn check(
+        explicit: Option<&Path>,
+        dir: &Path,
+    ) -> io::Result<Vec<(PathBuf, Vec<Diagnostic>)>> {
+        Self::get_paths(explicit, dir)?
+            .into_iter()
+            .map(|path| {
+                let config_str = fs::read_to_string(&path)?;
+                let diagnostics = validate(&path, &config_str);
+                Ok((path, diagnostics))
+            })
+            .collect()
+    }
 
-            let ret = Self::deserialize(de).map_err(|_| {
-                io::Error::new(io::ErrorKind::InvalidInput, "could not deserialize toml")
-            })?;
+    /// Every config file which applies to `dir`, in the order they are merged
+    pub fn get_paths(explicit: Option<&Path>, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
 
-            Ok(ret)
-        } else {
-            Ok(Self::default())
+        if let Some(path) = Self::get_path(explicit) {
+            // A config file which was asked for by name has to exist
+            let required = explicit.is_some() || Self::env_path().is_some();
+            if required || fs::exists(&path)? {
+                paths.push(path);
+            }
         }
+
+        paths.extend(Self::project_paths(dir));
+        Ok(paths)
     }
 
-    pub fn write_default() -> io::Result<PathBuf> {
-        let path = Self::get_path();
+    /// Each table which makes up the config for `dir`, in the order they are merged: the user
+    /// config, any `.lils.toml` files above `dir`, `LILS_*` environment variables, then the named
+    /// profile. Command line flags are applied over all of these by `override_with_args`.
+    pub fn layers(
+        explicit: Option<&Path>,
+        dir: &Path,
+        profile: Option<&str>,
+    ) -> io::Result<Vec<(Source, toml::Table)>> {
+        let mut layers = Vec::new();
+        let mut table = toml::Table::new();
+
+        for path in Self::get_paths(explicit, dir)? {
+            let layer = read_table(&path)?;
+            merge_tables(&mut table, layer.clone());
+            layers.push((Source::File(path), layer));
+        }
+
+        layers.extend(environment::env_layers()?);
+
+        if let Some(name) = profile {
+            let overlay = table
+                .get("profiles")
+                .and_then(|p| p.get(name))
+                .and_then(toml::Value::as_table)
+                .cloned()
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no profile named `{name}`"),
+                    )
+                })?;
+            layers.push((Source::Profile(name.to_string()), overlay));
+        }
+
+        Ok(layers)
+    }
+
+    /// Reads and merges every config layer. Unknown keys are reported as warnings, and the first
+    /// invalid file is returned as an error.
+    pub fn read(explicit: Option<&Path>, dir: &Path, profile: Option<&str>) -> io::Result<Self> {
+        let mut table = toml::Table::new();
+        for (_, layer) in Self::layers(explicit, dir, profile)? {
+            merge_tables(&mut table, layer);
+        }
+
+        Self::deserialize(table)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.message().to_string()))
+    }
+
+    /// Names of the profiles defined in the config which applies to `dir`
+    pub fn profile_names(dir: &Path) -> Vec<String> {
+        let Ok(config) = Self::read(None, dir, None) else {
+            return Vec::new();
+        };
+
+        config.profiles.into_keys().collect()
+    }
+
+    /// Every setting as a dotted key and its value, leaving out profiles
+    pub fn values(&self) -> Vec<(String, toml::Value)> {
+        let mut values = Vec::new();
+        if let Ok(toml::Value::Table(table)) = toml::Value::try_from(self) {
+            flatten_table(&table, "", &mut values);
+        }
+
+        values
+    }
+
+    /// Writes the default config to the user config path, unless a file is already there and
+    /// `force` isn't set
+    pub fn write_default(explicit: Option<&Path>, force: bool) -> io::Result<PathBuf> {
+        let path = Self::get_path(explicit).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "could not find a config directory")
+        })?;
+
+        if !force && fs::exists(&path)? {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "{} already exists, use --force to overwrite it",
+                    path.display()
+                ),
+            ));
+        }
 
         let default = Self::default();
         let output = toml::to_string_pretty(&default).unwrap();
 
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
         fs::write(&path, output)?;
         Ok(path)
     }
 
     pub fn override_with_args(mut self, matches: &ArgMatches) -> Self {
+        self.display.tty = io::stdout().is_terminal();
+
+        if let Some(mode) = matches
+            .subcommand_name()
+            .and_then(|s| OutputMode::from_str(s, true).ok())
+        {
+            self.display.mode = mode;
+        }
+        // The tree is always drawn from the whole subtree, down to the depth limit
+        if self.display.mode == OutputMode::Tree {
+            self.filter.recurse = true;
+        }
+
         if let Some(sorting_mode) = get_sorting_mode(matches) {
             self.sorting.mode = sorting_mode;
         }
         if let Some(recurse) = get_bool(matches, "recurse") {
             self.filter.recurse = recurse;
         }
-        if let Some(reverse) = get_bool(matches, "reverse") {
+        if let Some(true) = get_bool(matches, "flat") {
+            self.filter.recurse = true;
+            self.filter.flat = true;
+        }
+        if let Some(reverse) = get_flag(matches, "reverse", "no-reverse") {
             self.sorting.reverse = reverse;
         }
-        if let Some(show_hidden) = get_bool(matches, "all") {
+        if let Some(show_hidden) = get_flag(matches, "all", "no-all") {
             self.filter.hidden = show_hidden;
         }
-        if let Some(git) = get_bool(matches, "git") {
+        if let Some(git) = get_flag(matches, "git", "no-git") {
             self.filter.git = git;
         }
-        if let Some(icons) = get_bool(matches, "icons") {
+        if let Some(icons) = get_flag(matches, "icons", "no-icons") {
             self.display.icons = icons;
         }
-        if let Some(no_suffix) = get_bool(matches, "no-suffix") {
-            self.display.suffix = !no_suffix;
+        if let Some(suffix) = get_flag(matches, "suffix", "no-suffix") {
+            self.display.suffix = suffix;
+        }
+        if let Some(summary) = get_flag(matches, "summary", "no-summary") {
+            self.display.summary = summary;
+        }
+        if let Some(absolute) = get_flag(matches, "absolute", "no-absolute") {
+            self.display.absolute_paths = absolute;
+        }
+        if let Some(oneline) = get_flag(matches, "oneline", "no-oneline") {
+            self.display.oneline = oneline;
+        }
+        if let Some(prune) = get_flag(matches, "prune", "no-prune") {
+            self.display.prune = prune;
+        }
+        if let Some(max) = get_explicit::<usize>(matches, "max-per-dir") {
+            self.display.max_per_dir = max;
+        }
+        if let Some(hash) = get_explicit::<HashAlgorithm>(matches, "hash") {
+            self.display.hash = hash;
+        }
+        if let Some(lines) = get_flag(matches, "lines", "no-lines") {
+            self.display.lines = lines;
+        }
+        if let Some(media) = get_flag(matches, "media", "no-media") {
+            self.display.media = media;
+        }
+        if let Some(width) = get_explicit::<usize>(matches, "hash-width") {
+            self.display.hash_width = width;
+        }
+        if let Some(width) = get_explicit::<usize>(matches, "width") {
+            self.display.width = Some(width);
+        }
+        if let Some(hyperlinks) = get_flag(matches, "hyperlink", "no-hyperlink") {
+            self.display.hyperlinks = hyperlinks;
+        }
+        if let Some(across) = get_flag(matches, "across", "no-across") {
+            self.display.grid = if across {
+                GridDirection::Across
+            } else {
+                GridDirection::Down
+            };
+        }
+        if let Some(quoting) = get_explicit::<QuotingStyle>(matches, "quoting-style") {
+            self.display.quoting = quoting;
+        }
+        if let Some(color) = get_explicit::<ColorMode>(matches, "color") {
+            self.display.color = color;
+        } else if self.display.color == ColorMode::Auto {
+            // The conventions only refine the default, so config files and LILS_COLOR win
+            if let Some(color) = ColorMode::from_env() {
+                self.display.color = color;
+            }
+        }
+        if let Some(true) = get_bool(matches, "dereference") {
+            self.filter.dereference = Dereference::Always;
         }
-        if let Some(depth) = matches.get_one::<usize>("depth") {
-            let f_depth = if *depth == 0 { usize::MAX } else { *depth };
-            self.filter.depth = f_depth;
+        if let Some(true) = get_bool(matches, "dereference-args") {
+            self.filter.dereference = Dereference::Arguments;
         }
+        if let Some(true) = get_bool(matches, "no-dereference") {
+            self.filter.dereference = Dereference::Never;
+        }
+        if let Some(one_fs) = get_flag(matches, "one-file-system", "no-one-file-system") {
+            self.filter.one_file_system = one_fs;
+        }
+        if let Some(depth) = get_explicit::<usize>(matches, "depth") {
+            self.filter.depth = depth;
+        }
+        // A depth of 0 means there is no limit
+        if self.filter.depth == 0 {
+            self.filter.depth = usize::MAX;
+        }
+
+        self.display.color_enabled = self.display.color.enabled(self.display.tty);
 
         self
     }
 }
+
+fn read_table(path: &Path) -> io::Result<toml::Table> {
+    let config_str = fs::read_to_string(path)?;
+    let diagnostics = validate(path, &config_str);
+
+    for d in &diagnostics {
+        if d.severity == Severity::Error {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, d.to_string()));
+        }
+        eprintln!("{d}");
+    }
+
+    config_str
+        .parse::<toml::Table>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.message().to_string()))
+}
+
+fn flatten_table(table: &toml::Table, prefix: &str, output: &mut Vec<(String, toml::Value)>) {
+    for (key, value) in table {
+        let key = format!("{prefix}{key}");
+        match value {
+            toml::Value::Table(inner) => flatten_table(inner, &format!("{key}."), output),
+            _ => output.push((key, value.clone())),
+        }
+    }
+}
+
+/// Whether a dotted key is set in a table
+pub fn table_contains(table: &toml::Table, key: &str) -> bool {
+    let mut parts = key.split('.').peekable();
+    let mut current = table;
+    while let Some(part) = parts.next() {
+        match current.get(part) {
+            Some(toml::Value::Table(inner)) if parts.peek().is_some() => current = inner,
+            Some(_) => return parts.peek().is_none(),
+            None => return false,
+        }
+    }
+
+    false
+}
+
+// Overlays `over` onto `base`, merging nested tables key by key rather than replacing them
+fn merge_tables(base: &mut toml::Table, over: toml::Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(over)) => merge_tables(base, over),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
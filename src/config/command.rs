@@ -0,0 +1,226 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use clap::ArgMatches;
+use toml_edit::DocumentMut;
+
+use crate::config::{Config, Severity, Source, table_contains, validate};
+
+/// Where the config comes from, as given on the command line
+pub struct ConfigArgs<'a> {
+    pub explicit: Option<&'a Path>,
+    pub dir: &'a Path,
+    pub profile: Option<&'a str>,
+}
+
+/// Runs a `config` subcommand, returning whether it succeeded
+pub fn run(matches: &ArgMatches, args: &ConfigArgs) -> bool {
+    let result = match matches.subcommand() {
+        Some(("path", _)) => path(args),
+        Some(("show", _)) => show(args),
+        Some(("get", sub)) => get(args, sub.get_one::<String>("key").unwrap()),
+        Some(("set", sub)) => set(
+            args,
+            sub.get_one::<String>("key").unwrap(),
+            sub.get_one::<String>("value").unwrap(),
+        ),
+        Some(("edit", _)) => edit(args),
+        Some(("init", sub)) => init(args, sub.get_flag("force")),
+        Some(("check", _)) => return check(args),
+        _ => Ok(()),
+    };
+
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            false
+        }
+    }
+}
+
+fn user_path(args: &ConfigArgs) -> io::Result<PathBuf> {
+    Config::get_path(args.explicit)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "could not find a config directory"))
+}
+
+fn path(args: &ConfigArgs) -> io::Result<()> {
+    let user = user_path(args)?;
+    let paths = Config::get_paths(args.explicit, args.dir)?;
+
+    println!("{}", user.display());
+    for p in paths.iter().filter(|p| **p != user) {
+        println!("{}", p.display());
+    }
+
+    Ok(())
+}
+
+fn show(args: &ConfigArgs) -> io::Result<()> {
+    let layers = Config::layers(args.explicit, args.dir, args.profile)?;
+    let config = Config::read(args.explicit, args.dir, args.profile)?;
+
+    let lines: Vec<(String, Source)> = config
+        .values()
+        .into_iter()
+        .map(|(key, value)| {
+            // The last layer to set a key is the one which took effect
+            let source = layers
+                .iter()
+                .rev()
+                .find(|(_, table)| table_contains(table, &key))
+                .map(|(source, _)| source.clone())
+                .unwrap_or(Source::Default);
+            (format!("{key} = {value}"), source)
+        })
+        .collect();
+
+    println!(
+        "# Later sources take priority: default, user config, .lils.toml files, \
+         LILS_* environment variables, profile, command line flags"
+    );
+    let width = lines.iter().map(|(l, _)| l.len()).max().unwrap_or(0);
+    for (line, source) in lines {
+        println!("{line:width$}  # {source}");
+    }
+
+    Ok(())
+}
+
+fn get(args: &ConfigArgs, key: &str) -> io::Result<()> {
+    let config = Config::read(args.explicit, args.dir, args.profile)?;
+    let (_, value) = config
+        .values()
+        .into_iter()
+        .find(|(k, _)| k == key)
+        .ok_or_else(|| unknown_key(key))?;
+
+    match value {
+        toml::Value::String(s) => println!("{s}"),
+        value => println!("{value}"),
+    }
+
+    Ok(())
+}
+
+fn set(args: &ConfigArgs, key: &str, value: &str) -> io::Result<()> {
+    let path = user_path(args)?;
+    let contents = if fs::exists(&path)? {
+        fs::read_to_string(&path)?
+    } else {
+        String::new()
+    };
+
+    let mut doc: DocumentMut = contents
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{e}")))?;
+
+    // Values are read as TOML where possible, so `true` and `3` aren't stored as strings
+    let value = value
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| toml_edit::Value::from(value));
+
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let last = parts.pop().ok_or_else(|| unknown_key(key))?;
+    let mut item = doc.as_item_mut();
+    for part in parts {
+        item = &mut item[part];
+        if item.is_none() {
+            // Written as `[section]` headers, in the same shape as `config init`
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            *item = toml_edit::Item::Table(table);
+        } else if !item.is_table_like() {
+            return Err(unknown_key(key));
+        }
+    }
+    item[last] = toml_edit::value(value);
+
+    // Refuse to write anything which wouldn't be read back cleanly, or a key which isn't known.
+    // Unknown keys which were already in the file are left for `config check` to point out.
+    let output = doc.to_string();
+    for d in validate(&path, &output) {
+        match d.severity {
+            Severity::Error => return Err(io::Error::new(io::ErrorKind::InvalidInput, d.message)),
+            Severity::Warning if d.key.as_deref() == Some(key) => return Err(unknown_key(key)),
+            Severity::Warning => {}
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, output)
+}
+
+fn edit(args: &ConfigArgs) -> io::Result<()> {
+    let path = user_path(args)?;
+    if !fs::exists(&path)? {
+        Config::write_default(Some(&path), false)?;
+    }
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+
+    // Run through the shell so editors given with arguments, like `code --wait`, work
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(&path)
+        .status()?;
+
+    if !status.success() {
+        return Err(io::Error::other(format!("{editor} exited with {status}")));
+    }
+
+    for d in validate(&path, &fs::read_to_string(&path)?) {
+        eprintln!("{d}");
+    }
+
+    Ok(())
+}
+
+fn init(args: &ConfigArgs, force: bool) -> io::Result<()> {
+    let path = Config::write_default(args.explicit, force)?;
+    println!("Successfully wrote default config to {}", path.display());
+    Ok(())
+}
+
+fn check(args: &ConfigArgs) -> bool {
+    let results = match Config::check(args.explicit, args.dir) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error reading config: {e}");
+            return false;
+        }
+    };
+
+    if results.is_empty() {
+        println!("No config files found");
+    }
+
+    let mut valid = true;
+    for (path, diagnostics) in results {
+        if diagnostics.is_empty() {
+            println!("{}: ok", path.display());
+        }
+        for d in diagnostics {
+            valid &= d.severity != Severity::Error;
+            eprintln!("{d}");
+        }
+    }
+
+    valid
+}
+
+fn unknown_key(key: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("unknown config key `{key}`"),
+    )
+}
@@ -0,0 +1,82 @@
+use std::{env, io};
+
+use serde::Deserialize;
+
+use crate::config::{Config, Source};
+
+/// Environment variables which can set config values, and the keys they set
+const VARIABLES: [(&str, &str); 20] = [
+    ("LILS_SORT", "sorting.mode"),
+    ("LILS_REVERSE", "sorting.reverse"),
+    ("LILS_MODE", "display.mode"),
+    ("LILS_ICONS", "display.icons"),
+    ("LILS_SUFFIX", "display.suffix"),
+    ("LILS_GRID", "display.grid"),
+    ("LILS_QUOTING", "display.quoting"),
+    ("LILS_COLOR", "display.color"),
+    ("LILS_HYPERLINKS", "display.hyperlinks"),
+    ("LILS_ONELINE", "display.oneline"),
+    ("LILS_ABSOLUTE", "display.absolute_paths"),
+    ("LILS_SUMMARY", "display.summary"),
+    ("LILS_HASH", "display.hash"),
+    ("LILS_LINES", "display.lines"),
+    ("LILS_MEDIA", "display.media"),
+    ("LILS_DEPTH", "filter.depth"),
+    ("LILS_GIT", "filter.git"),
+    ("LILS_ALL", "filter.hidden"),
+    ("LILS_DEREFERENCE", "filter.dereference"),
+    ("LILS_ONE_FILE_SYSTEM", "filter.one_file_system"),
+];
+
+/// The profile named by `LILS_PROFILE`, used when `--profile` isn't given
+pub fn env_profile() -> Option<String> {
+    env::var("LILS_PROFILE").ok().filter(|p| !p.is_empty())
+}
+
+/// A layer for each `LILS_*` variable which is set, converted to the type of the key it sets
+pub fn env_layers() -> io::Result<Vec<(Source, toml::Table)>> {
+    let defaults = Config::default().values();
+    let mut layers = Vec::new();
+
+    for (var, key) in VARIABLES {
+        let Some(raw) = env::var(var).ok().filter(|v| !v.is_empty()) else {
+            continue;
+        };
+
+        let default = defaults.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{var} has an invalid value `{raw}`"),
+            )
+        };
+        let value = match default {
+            Some(toml::Value::Boolean(_)) => parse_bool(&raw).map(toml::Value::Boolean),
+            Some(toml::Value::Integer(_)) => raw.parse().ok().map(toml::Value::Integer),
+            _ => Some(toml::Value::String(raw.to_lowercase())),
+        }
+        .ok_or_else(invalid)?;
+
+        let (section, name) = key.split_once('.').unwrap_or(("", key));
+        let mut inner = toml::Table::new();
+        inner.insert(name.into(), value);
+        let mut table = toml::Table::new();
+        table.insert(section.into(), toml::Value::Table(inner));
+
+        // Checked on its own, so that a bad value is blamed on the variable rather than turning
+        // up when every layer is merged
+        Config::deserialize(table.clone()).map_err(|_| invalid())?;
+
+        layers.push((Source::Env(var.to_string()), table));
+    }
+
+    Ok(layers)
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
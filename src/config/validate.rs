@@ -0,0 +1,139 @@
+use std::{
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use toml::de::{DeTable, DeValue};
+
+use crate::config::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in a config file, located by line and column
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub key: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{severity}: {}:{}:{}: {}",
+            self.path.display(),
+            self.line,
+            self.column,
+            self.message
+        )?;
+        if let Some(key) = &self.key {
+            write!(f, " (at `{key}`)")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks a config file's contents, returning any errors followed by warnings for unknown keys
+pub fn validate(path: &Path, contents: &str) -> Vec<Diagnostic> {
+    let diagnostic = |severity, span: Option<Range<usize>>, key, message| {
+        let (line, column) = span.map(|s| position(contents, s.start)).unwrap_or((1, 1));
+        Diagnostic {
+            severity,
+            path: path.to_path_buf(),
+            line,
+            column,
+            key,
+            message,
+        }
+    };
+
+    let table = match DeTable::parse(contents) {
+        Ok(t) => t,
+        Err(e) => {
+            return vec![diagnostic(
+                Severity::Error,
+                e.span(),
+                None,
+                e.message().into(),
+            )];
+        }
+    };
+
+    let mut unknown = Vec::new();
+    let de = toml::Deserializer::parse(contents);
+    let result = de.and_then(|de| {
+        serde_ignored::deserialize(de, |p| unknown.push(p.to_string())).map(|_: Config| ())
+    });
+
+    if let Err(e) = result {
+        let key = e
+            .span()
+            .and_then(|s| key_at(table.get_ref(), s.start, Vec::new()));
+        return vec![diagnostic(
+            Severity::Error,
+            e.span(),
+            key,
+            e.message().into(),
+        )];
+    }
+
+    unknown
+        .into_iter()
+        .map(|key| {
+            let parts: Vec<&str> = key.split('.').collect();
+            let span = key_span(table.get_ref(), &parts);
+            diagnostic(Severity::Warning, span, Some(key), "unknown key".into())
+        })
+        .collect()
+}
+
+// 1-based line and column of a byte offset
+fn position(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+// Dotted path of the key whose value contains `offset`
+fn key_at(table: &DeTable, offset: usize, parents: Vec<String>) -> Option<String> {
+    for (key, value) in table.iter() {
+        let mut path = parents.clone();
+        path.push(key.get_ref().to_string());
+
+        if let DeValue::Table(inner) = value.get_ref()
+            && let Some(found) = key_at(inner, offset, path.clone())
+        {
+            return Some(found);
+        }
+        if value.span().contains(&offset) {
+            return Some(path.join("."));
+        }
+    }
+
+    None
+}
+
+fn key_span(table: &DeTable, parts: &[&str]) -> Option<Range<usize>> {
+    let (first, rest) = parts.split_first()?;
+    let (key, value) = table.iter().find(|(k, _)| k.get_ref() == first)?;
+
+    match value.get_ref() {
+        DeValue::Table(inner) if !rest.is_empty() => key_span(inner, rest),
+        _ => Some(key.span()),
+    }
+}
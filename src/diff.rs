@@ -0,0 +1,177 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use serde::Serialize;
+
+use crate::{
+    files::{EntryType, FsEntry},
+    util::unix_seconds,
+};
+
+/// How an entry differs between the left and right listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// Only in the left listing
+    Removed,
+    /// Only in the right listing
+    Added,
+    Modified,
+    Identical,
+}
+
+/// A piece of metadata which differs, with its left and right values
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "field", rename_all = "lowercase")]
+pub enum Change {
+    Type {
+        left: &'static str,
+        right: &'static str,
+    },
+    Size {
+        left: u64,
+        right: u64,
+    },
+    /// Permission bits, in octal
+    Mode {
+        left: String,
+        right: String,
+    },
+    /// Modification time, in seconds since the Unix epoch
+    Modified {
+        left: u64,
+        right: u64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Difference {
+    /// Path relative to the roots being compared
+    pub path: PathBuf,
+    pub status: Status,
+    pub changes: Vec<Change>,
+    /// The entry as listed, from the right side unless it was removed
+    pub entry: Rc<FsEntry>,
+}
+
+// The entries with the same name in the left and right directories
+type Sides<'a> = (Option<&'a Rc<FsEntry>>, Option<&'a Rc<FsEntry>>);
+
+/// Compares the subtrees of two directories, in name order. A directory which is only on one
+/// side is reported once, without its contents.
+pub fn compare(left: &FsEntry, right: &FsEntry, identical: bool) -> Vec<Difference> {
+    let mut output = Vec::new();
+    compare_children(left, right, Path::new(""), identical, &mut output);
+    output
+}
+
+fn compare_children(
+    left: &FsEntry,
+    right: &FsEntry,
+    parent: &Path,
+    identical: bool,
+    output: &mut Vec<Difference>,
+) {
+    let mut names: BTreeMap<OsString, Sides> = BTreeMap::new();
+    for child in left.children.iter().flatten() {
+        names.entry(child.raw_name.clone()).or_default().0 = Some(child);
+    }
+    for child in right.children.iter().flatten() {
+        names.entry(child.raw_name.clone()).or_default().1 = Some(child);
+    }
+
+    for (name, sides) in names {
+        let path = parent.join(&name);
+        let (status, changes, entry) = match sides {
+            (Some(l), None) => (Status::Removed, Vec::new(), l),
+            (None, Some(r)) => (Status::Added, Vec::new(), r),
+            (Some(l), Some(r)) => {
+                let changes = changes(l, r);
+                if changes.is_empty() {
+                    (Status::Identical, changes, r)
+                } else {
+                    (Status::Modified, changes, r)
+                }
+            }
+            (None, None) => continue,
+        };
+
+        if status != Status::Identical || identical {
+            output.push(Difference {
+                path: path.clone(),
+                status,
+                changes,
+                entry: entry.clone(),
+            });
+        }
+
+        // Only directories read on both sides have contents to compare
+        if let (Some(l), Some(r)) = sides
+            && l.children.is_some()
+            && r.children.is_some()
+        {
+            compare_children(l, r, &path, identical, output);
+        }
+    }
+}
+
+fn changes(left: &FsEntry, right: &FsEntry) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if type_name(left.e_type) != type_name(right.e_type) {
+        changes.push(Change::Type {
+            left: type_name(left.e_type),
+            right: type_name(right.e_type),
+        });
+        return changes;
+    }
+
+    let (l_mode, r_mode) = (left.perms.mode() & 0o7777, right.perms.mode() & 0o7777);
+    if l_mode != r_mode {
+        changes.push(Change::Mode {
+            left: format!("{l_mode:04o}"),
+            right: format!("{r_mode:04o}"),
+        });
+    }
+
+    // A directory's size and time change with its contents, which are compared separately
+    if left.e_type == EntryType::Directory {
+        return changes;
+    }
+
+    if left.size != right.size {
+        changes.push(Change::Size {
+            left: left.size,
+            right: right.size,
+        });
+    }
+    // Copies don't always keep sub-second precision, so times are compared to the second
+    let (l_time, r_time) = (
+        unix_seconds(left.times.modified),
+        unix_seconds(right.times.modified),
+    );
+    if l_time != r_time {
+        changes.push(Change::Modified {
+            left: l_time,
+            right: r_time,
+        });
+    }
+
+    changes
+}
+
+pub fn type_name(e_type: EntryType) -> &'static str {
+    match e_type {
+        EntryType::Directory => "directory",
+        EntryType::File(_) => "file",
+        EntryType::BlockDevice => "block device",
+        EntryType::CharDevice => "char device",
+        EntryType::Symlink => "symlink",
+        EntryType::Socket => "socket",
+        EntryType::Fifo => "fifo",
+    }
+}
@@ -1,39 +1,49 @@
 use std::{
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
     fs::{self, Permissions},
     io,
-    os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, MetadataExt, PermissionsExt},
+    },
     path::{Path, PathBuf},
     rc::Rc,
     time,
 };
 
+use clap::ValueEnum;
 use ignore::WalkBuilder;
+use pathdiff::diff_paths;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     config::Config,
     util::{get_group_by_gid, get_user_by_uid},
 };
 
+/// Which symlinks are followed, equivalent to ls' default, `-H` and `-L`
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dereference {
+    #[default]
+    Never,
+    Arguments,
+    Always,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EntryType {
     Directory,
+    /// A regular file, the only type whose contents are ever read
     File(FileType),
     BlockDevice,
     CharDevice,
     Symlink,
     Socket,
+    Fifo,
 }
 
 impl EntryType {
-    fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let path = path.as_ref();
-        let metadata = fs::metadata(path)?;
-        let ext = path.extension().unwrap_or(OsStr::new("")).to_string_lossy();
-
-        Self::from_metadata(&metadata, path)
-    }
-
     fn from_metadata(metadata: &fs::Metadata, path: &Path) -> io::Result<Self> {
         if metadata.file_type().is_dir() {
             return Ok(Self::Directory);
@@ -59,6 +69,10 @@ impl EntryType {
             return Ok(Self::Socket);
         }
 
+        if metadata.file_type().is_fifo() {
+            return Ok(Self::Fifo);
+        }
+
         Ok(Self::File(FileType::Text))
     }
 }
@@ -67,16 +81,14 @@ impl EntryType {
 pub enum FileType {
     Text,
     Executable,
+    Image,
+    Audio,
+    Video,
 }
 
 impl FileType {
     pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
         let path = path.as_ref();
-        let ext = path
-            .extension()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
         let metadata = fs::metadata(path)?;
         let permissions = metadata.permissions();
 
@@ -85,18 +97,36 @@ impl FileType {
             return Ok(Self::Executable);
         }
 
-        // Otherwise match on the file extension
-        Ok(match ext.as_str() {
-            "txt" | "md" => Self::Text,
+        Ok(Self::from_extension(path))
+    }
+
+    /// Classifies a file by its extension alone
+    pub fn from_extension(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+
+        match ext.as_str() {
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "ico" | "tif" | "tiff" | "svg"
+            | "avif" | "heic" => Self::Image,
+            "mp3" | "flac" | "wav" | "ogg" | "oga" | "opus" | "m4a" | "aac" | "aiff" | "wma" => {
+                Self::Audio
+            }
+            "mp4" | "m4v" | "mov" | "mkv" | "webm" | "avi" | "wmv" | "flv" | "mpg" | "mpeg"
+            | "ogv" => Self::Video,
             _ => Self::Text,
-        })
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Times {
     pub modified: time::SystemTime,
+    #[expect(dead_code, reason = "nothing sorts or displays by creation time yet")]
     pub created: time::SystemTime,
+    #[expect(dead_code, reason = "nothing sorts or displays by access time yet")]
     pub access: time::SystemTime,
 }
 
@@ -104,6 +134,7 @@ pub type EntryChildren = Vec<Rc<FsEntry>>;
 #[derive(Debug, Clone)]
 pub struct FsEntry {
     pub name: String,
+    pub raw_name: OsString,
     pub uid: u32,
     pub u_name: Option<String>,
     pub gid: u32,
@@ -112,103 +143,184 @@ pub struct FsEntry {
     pub path: PathBuf,
     pub e_type: EntryType,
     pub times: Times,
+    pub size: u64,
+    /// Whether this entry is on a different device than the directory containing it
+    pub mount_point: bool,
     pub children: Option<EntryChildren>,
+    pub skipped: Skipped,
+}
+
+/// Counts of directory entries left out of `children` by the filters
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Skipped {
+    pub hidden: usize,
+    pub ignored: usize,
 }
 
 impl FsEntry {
     pub fn new<P: AsRef<Path>>(
         path: P,
         e_type: EntryType,
-        times: Times,
         children: Option<Vec<Rc<FsEntry>>>,
-        uid: u32,
-        gid: u32,
-        perms: Permissions,
+        metadata: &fs::Metadata,
     ) -> Self {
         let path = path.as_ref();
-        let name = path
-            .file_name()
-            .unwrap_or(OsStr::new(""))
-            .to_string_lossy()
-            .to_string();
+        let raw_name = path.file_name().unwrap_or(OsStr::new("")).to_os_string();
+        let name = raw_name.to_string_lossy().to_string();
+        let modified = metadata.modified().unwrap_or(time::UNIX_EPOCH);
+        // Not every filesystem records a creation time
+        let times = Times {
+            access: metadata.accessed().unwrap_or(modified),
+            created: metadata.created().unwrap_or(modified),
+            modified,
+        };
 
         Self {
             name,
-            uid,
-            u_name: get_user_by_uid(uid),
-            gid,
-            g_name: get_group_by_gid(gid),
-            perms,
+            raw_name,
+            uid: metadata.uid(),
+            u_name: get_user_by_uid(metadata.uid()),
+            gid: metadata.gid(),
+            g_name: get_group_by_gid(metadata.gid()),
+            perms: metadata.permissions(),
             path: path.into(),
             e_type,
             times,
+            size: metadata.size(),
+            mount_point: false,
             children,
+            skipped: Skipped::default(),
         }
     }
 
+    /// Reads every path given on the command line. Any which can't be read are reported and left
+    /// out, and the flag says whether all of them could be.
+    pub fn from_paths(paths: &[PathBuf], config: &Config) -> (Vec<Self>, bool) {
+        let mut entries = Vec::new();
+        let mut all_read = true;
+
+        for path in paths {
+            match Self::from_path(path, config) {
+                Ok(e) if config.filter.flat => entries.push(e.flattened()),
+                Ok(e) => entries.push(e),
+                Err(e) => {
+                    eprintln!("{}: {e}", path.display());
+                    all_read = false;
+                }
+            }
+        }
+
+        (entries, all_read)
+    }
+
     pub fn from_path<P: AsRef<Path>>(path: P, config: &Config) -> io::Result<Self> {
-        Self::create_from_path(path, config, 0)
+        Self::create_from_path(path, config, 0, &mut Vec::new())
     }
 
+    // `ancestors` holds the device and inode of each directory above this entry, so that following
+    // a symlink back up the tree is caught instead of recursing forever
     fn create_from_path<P: AsRef<Path>>(
         path: P,
         config: &Config,
         depth: usize,
+        ancestors: &mut Vec<(u64, u64)>,
     ) -> io::Result<Self> {
         let path = path.as_ref();
-        let metadata = fs::metadata(path)?;
-        let e_type = EntryType::from_metadata(&metadata, path)?;
-        let times = Times {
-            access: metadata.accessed().unwrap(),
-            created: metadata.created().unwrap(),
-            modified: metadata.modified().unwrap(),
+        let follow = match config.filter.dereference {
+            Dereference::Never => false,
+            Dereference::Arguments => depth == 0,
+            Dereference::Always => true,
         };
+        // A broken link can't be followed, so it is listed as the link itself
+        let metadata = if follow {
+            fs::metadata(path).or_else(|_| fs::symlink_metadata(path))?
+        } else {
+            fs::symlink_metadata(path)?
+        };
+        let e_type = EntryType::from_metadata(&metadata, path)?;
 
         let mut children = None;
+        let mut skipped = Skipped::default();
+        let id = (metadata.dev(), metadata.ino());
+        let mount_point = ancestors.last().is_some_and(|(dev, _)| *dev != id.0);
+        let other_fs = ancestors.first().is_some_and(|(dev, _)| *dev != id.0);
 
-        if e_type == EntryType::Directory && config.filter.recurse && depth < config.filter.depth
-            || depth == 0
+        if e_type == EntryType::Directory
+            && (config.filter.recurse && depth < config.filter.depth || depth == 0)
+            && !(config.filter.one_file_system && other_fs)
         {
-            children = Some(Self::get_children(path, config, depth)?);
+            if ancestors.contains(&id) {
+                eprintln!("{}: not following symlink loop", path.display());
+            } else {
+                ancestors.push(id);
+                let loaded = Self::get_children(path, config, depth, ancestors);
+                ancestors.pop();
+
+                if config.display.summary {
+                    skipped = Self::count_skipped(path, config, loaded.len())?;
+                }
+                children = Some(loaded);
+            }
         }
 
-        Ok(Self::new(
-            path,
-            e_type,
-            times,
-            children,
-            metadata.uid(),
-            metadata.gid(),
-            metadata.permissions(),
-        ))
+        let mut entry = Self::new(path, e_type, children, &metadata);
+        entry.skipped = skipped;
+        entry.mount_point = mount_point;
+        Ok(entry)
+    }
+
+    // Hidden entries are counted directly, and whatever else the walk left out was ignored
+    fn count_skipped(path: &Path, config: &Config, loaded: usize) -> io::Result<Skipped> {
+        let mut total: usize = 0;
+        let mut hidden = 0;
+        for e in fs::read_dir(path)?.flatten() {
+            total += 1;
+            if !config.filter.hidden && e.file_name().as_bytes().starts_with(b".") {
+                hidden += 1;
+            }
+        }
+
+        Ok(Skipped {
+            hidden,
+            ignored: total.saturating_sub(hidden + loaded),
+        })
     }
 
     fn get_children<P: AsRef<Path>>(
         path: P,
         config: &Config,
         depth: usize,
-    ) -> io::Result<EntryChildren> {
+        ancestors: &mut Vec<(u64, u64)>,
+    ) -> EntryChildren {
         let path = path.as_ref();
         let walk = WalkBuilder::new(path)
-            .hidden(config.filter.hidden)
+            .hidden(!config.filter.hidden)
             .ignore(false)
             .require_git(true)
             .git_ignore(config.filter.git)
+            // Whether to follow each link is decided by `create_from_path`, which can fall back
+            // to listing a broken one as the link itself
+            .follow_links(false)
             .max_depth(Some(1))
             .build();
         let mut children = Vec::new();
 
-        for e in walk.flatten() {
-            if e.file_name() != "./" {
-                children.push(Rc::new(Self::create_from_path(
-                    e.path(),
-                    config,
-                    depth + 1,
-                )?));
+        // The walk yields the directory itself first, at depth 0. Like ls, an entry which can't be
+        // read is reported without losing the rest of the listing.
+        for e in walk {
+            match e {
+                Ok(e) if e.depth() > 0 => {
+                    match Self::create_from_path(e.path(), config, depth + 1, ancestors) {
+                        Ok(child) => children.push(Rc::new(child)),
+                        Err(err) => eprintln!("{}: {err}", e.path().display()),
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => eprintln!("{err}"),
             }
         }
 
-        Ok(children)
+        children
     }
 
     pub fn get_all_dirs(&self) -> Vec<Rc<Self>> {
@@ -222,4 +334,38 @@ impl FsEntry {
 
         output
     }
+
+    /// A copy of this entry whose children are every entry in its subtree, named by their path
+    /// relative to it, so the whole tree can be listed and sorted as one
+    pub fn flattened(&self) -> Self {
+        let mut flat = self.clone();
+        flat.children = self.children.as_ref().map(|_| {
+            let mut output = Vec::new();
+            self.collect_descendants(&self.path, &mut output, &mut flat.skipped);
+            output
+        });
+
+        flat
+    }
+
+    fn collect_descendants(&self, root: &Path, output: &mut EntryChildren, skipped: &mut Skipped) {
+        let Some(children) = self.children.as_ref() else {
+            return;
+        };
+
+        for child in children {
+            skipped.hidden += child.skipped.hidden;
+            skipped.ignored += child.skipped.ignored;
+
+            let mut entry = FsEntry::clone(child);
+            entry.children = None;
+            if let Some(relative) = diff_paths(&child.path, root) {
+                entry.raw_name = relative.into_os_string();
+                entry.name = entry.raw_name.to_string_lossy().to_string();
+            }
+
+            output.push(Rc::new(entry));
+            child.collect_descendants(root, output, skipped);
+        }
+    }
 }
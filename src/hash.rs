@@ -0,0 +1,67 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::util::par_map;
+
+/// How file contents are hashed for the hash column, if at all
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    None,
+    /// Cryptographic, for checking files against published checksums
+    Sha256,
+    /// Much faster, for spotting duplicate or changed files
+    Xxh3,
+}
+
+/// Hashes each file across all available cores, giving None for any which can't be read
+pub fn hash_files(paths: &[&Path], algorithm: HashAlgorithm) -> Vec<Option<String>> {
+    if algorithm == HashAlgorithm::None {
+        return vec![None; paths.len()];
+    }
+
+    par_map(paths, |p| hash_file(p, algorithm).ok())
+}
+
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; 64 * 1024];
+
+    match algorithm {
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            read_chunks(&mut file, &mut buffer, |b| hasher.update(b))?;
+            Ok(hex(&hasher.finalize()))
+        }
+        HashAlgorithm::Xxh3 => {
+            let mut hasher = Xxh3::new();
+            read_chunks(&mut file, &mut buffer, |b| hasher.update(b))?;
+            Ok(format!("{:016x}", hasher.digest()))
+        }
+        HashAlgorithm::None => Ok(String::new()),
+    }
+}
+
+fn read_chunks<F: FnMut(&[u8])>(file: &mut File, buffer: &mut [u8], mut f: F) -> io::Result<()> {
+    loop {
+        match file.read(buffer) {
+            Ok(0) => return Ok(()),
+            Ok(n) => f(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,36 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Counts lines like `wc -l`, except that a last line without a newline still counts. Gives None
+/// on finding a NUL byte, since text never contains one and the file must be binary.
+pub fn count_lines(path: &Path) -> io::Result<Option<usize>> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut lines = 0;
+    let mut last = b'\n';
+
+    loop {
+        let n = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let chunk = &buffer[..n];
+        if chunk.contains(&0) {
+            return Ok(None);
+        }
+
+        lines += chunk.iter().filter(|b| **b == b'\n').count();
+        last = chunk[n - 1];
+    }
+
+    if last != b'\n' {
+        lines += 1;
+    }
+
+    Ok(Some(lines))
+}
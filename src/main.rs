@@ -1,59 +1,206 @@
-use std::{io, path::PathBuf};
+use std::{io, path::PathBuf, process};
 
 use clap::ArgMatches;
+use clap_complete::CompleteEnv;
+use nix::sys::signal::{SigHandler, Signal, signal};
 
+#[cfg(target_os = "linux")]
+use crate::watch::watch;
 use crate::{
-    cli::get_cli,
-    config::Config,
+    cli::{get_cli, get_explicit, write_completions, write_manpage},
+    config::{
+        Config,
+        command::{self, ConfigArgs},
+        env_profile,
+    },
+    diff::{Status, compare},
     files::FsEntry,
-    output::{long, short},
+    output::{print, print_diff, print_diff_json},
+    snapshot::Snapshot,
 };
 
 mod cli;
 mod config;
+mod diff;
 mod files;
+mod hash;
+mod lines;
+mod media;
 mod output;
+mod quoting;
+mod snapshot;
 mod sorting;
 mod style;
 mod util;
+#[cfg(target_os = "linux")]
+mod watch;
 
 fn main() {
+    // Exit quietly like other coreutils when piped into something like `head`
+    unsafe {
+        let _ = signal(Signal::SIGPIPE, SigHandler::SigDfl);
+    }
+
+    // Answers shell completion requests, when run by a script from `lils completions`
+    CompleteEnv::with_factory(get_cli).complete();
+
     let cli = get_cli();
     let matches = cli.get_matches();
 
-    if let Some(("config", _)) = matches.subcommand() {
-        match Config::write_default() {
-            Ok(p) => println!(
-                "Successfully wrote default config to {}",
-                p.to_string_lossy()
-            ),
-            Err(e) => eprintln!("Error writing default config: {e}"),
+    let result = match matches.subcommand() {
+        Some(("completions", sub)) => {
+            Some(write_completions(sub.get_one::<String>("shell").unwrap()))
+        }
+        Some(("manpage", _)) => Some(write_manpage()),
+        _ => None,
+    };
+    if let Some(result) = result {
+        if let Err(e) = result {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    let config_path = matches.get_one::<PathBuf>("config").map(PathBuf::as_path);
+
+    let dir = matches
+        .get_one::<PathBuf>("path")
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from("./"));
+
+    let profile = matches
+        .get_one::<String>("profile")
+        .cloned()
+        .or_else(env_profile);
+    let profile = profile.as_deref();
+
+    if let Some(("config", sub)) = matches.subcommand() {
+        let args = ConfigArgs {
+            explicit: config_path,
+            dir: &dir,
+            profile,
+        };
+        process::exit(if command::run(sub, &args) { 0 } else { 1 });
+    }
+
+    let config = match Config::read(config_path, &dir, profile) {
+        Ok(c) => c.override_with_args(&matches),
+        Err(e) => {
+            eprintln!("Error reading config: {e}");
+            process::exit(1);
+        }
+    };
+    if let Some(("diff", sub)) = matches.subcommand() {
+        match diff(sub, config) {
+            Ok(same) => process::exit(if same { 0 } else { 1 }),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                process::exit(2);
+            }
+        }
+    }
+
+    if let Some(("snapshot", sub)) = matches.subcommand() {
+        match snapshot(&matches, sub, config) {
+            Ok(same) => process::exit(if same { 0 } else { 1 }),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                process::exit(2);
+            }
         }
     }
 
-    let config = Config::read().unwrap().override_with_args(&matches);
-    let res = display(matches, config);
+    match display(matches, config) {
+        Ok(true) => {}
+        // Like ls, an argument which couldn't be listed is serious trouble
+        Ok(false) => process::exit(2),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+    }
 }
 
-fn display(matches: ArgMatches, config: Config) -> io::Result<()> {
+// Lists the paths, returning whether every one of them could be read
+fn display(matches: ArgMatches, config: Config) -> io::Result<bool> {
     let paths: Vec<PathBuf> = matches
         .get_many::<PathBuf>("path")
         .unwrap()
         .cloned()
         .collect();
 
-    let entries: Vec<FsEntry> = paths
-        .iter()
-        .flat_map(|p| FsEntry::from_path(p, &config))
-        .collect();
+    if matches.get_flag("watch") {
+        #[cfg(target_os = "linux")]
+        return watch(&paths, config).map(|()| true);
+        #[cfg(not(target_os = "linux"))]
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "--watch is only supported on Linux",
+        ));
+    }
+
+    let (roots, all_read) = FsEntry::from_paths(&paths, &config);
+    print(&roots, &config);
 
-    match matches.subcommand() {
-        Some(("tree", _)) => {
-            println!("tree")
+    Ok(all_read)
+}
+
+// Compares two directories like diff(1), returning whether they were the same
+fn diff(matches: &ArgMatches, mut config: Config) -> io::Result<bool> {
+    config.filter.recurse = true;
+
+    let mut roots = Vec::new();
+    for side in ["left", "right"] {
+        let path = matches.get_one::<PathBuf>(side).unwrap();
+        let entry = FsEntry::from_path(path, &config)?;
+        if entry.children.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a directory", path.display()),
+            ));
         }
-        Some(("long", _)) => long(&entries, &config),
-        _ => short(&entries, &config),
+        roots.push(entry);
+    }
+
+    show_differences(matches, &roots[0], &roots[1], &config)
+}
+
+// Saves a listing, or compares one against the directory as it is now, returning whether nothing
+// had changed
+fn snapshot(matches: &ArgMatches, sub: &ArgMatches, mut config: Config) -> io::Result<bool> {
+    let (action, sub) = sub.subcommand().unwrap();
+    let file = sub.get_one::<PathBuf>("file").unwrap();
+
+    if action == "save" {
+        let path = matches.get_one::<PathBuf>("path").unwrap();
+        Snapshot::new(&FsEntry::from_path(path, &config)?).save(file)?;
+        return Ok(true);
+    }
+
+    let snapshot = Snapshot::load(file)?;
+    let path = get_explicit::<PathBuf>(matches, "path").unwrap_or_else(|| snapshot.path.clone());
+    // Only directories which were read when saving are compared, so this just has to go as deep
+    config.filter.recurse = true;
+    let current = FsEntry::from_path(&path, &config)?;
+
+    let mut saved = snapshot.entry();
+    saved.path = file.clone();
+    show_differences(sub, &saved, &current, &config)
+}
+
+fn show_differences(
+    matches: &ArgMatches,
+    left: &FsEntry,
+    right: &FsEntry,
+    config: &Config,
+) -> io::Result<bool> {
+    let differences = compare(left, right, matches.get_flag("identical"));
+    if matches.get_flag("json") {
+        print_diff_json(&differences, &left.path, &right.path)?;
+    } else {
+        print_diff(&differences, config);
     }
 
-    Ok(())
+    Ok(differences.iter().all(|d| d.status == Status::Identical))
 }
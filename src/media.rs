@@ -0,0 +1,562 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+use crate::files::FileType;
+
+/// Dimensions for an image, or the running time for audio and video, as shown in the media column
+pub fn media_info(path: &Path, f_type: FileType) -> Option<String> {
+    match f_type {
+        FileType::Image => dimensions(path).map(|(w, h)| format!("{w}x{h}")),
+        FileType::Audio | FileType::Video => duration(path).map(format_duration),
+        _ => None,
+    }
+}
+
+/// Width and height of a PNG, GIF, WebP or JPEG image, read from its header
+pub fn dimensions(path: &Path) -> Option<(u64, u64)> {
+    read_dimensions(&mut BufReader::new(File::open(path).ok()?))
+}
+
+fn read_dimensions<R: Read + Seek>(file: &mut R) -> Option<(u64, u64)> {
+    let mut h = [0; 30];
+    file.read_exact(&mut h).ok()?;
+
+    if h.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some((be(&h[16..20]), be(&h[20..24])));
+    }
+    if h.starts_with(b"GIF8") {
+        return Some((le(&h[6..8]), le(&h[8..10])));
+    }
+    if h.starts_with(b"RIFF") && &h[8..12] == b"WEBP" {
+        return webp(&h);
+    }
+    if h.starts_with(&[0xFF, 0xD8]) {
+        file.seek(SeekFrom::Start(2)).ok()?;
+        return jpeg(file);
+    }
+
+    None
+}
+
+// The first chunk says which of the three WebP formats this is, each storing its size differently
+fn webp(h: &[u8; 30]) -> Option<(u64, u64)> {
+    match &h[12..16] {
+        b"VP8 " => Some((le(&h[26..28]) & 0x3FFF, le(&h[28..30]) & 0x3FFF)),
+        b"VP8L" => {
+            let bits = le(&h[21..25]);
+            Some(((bits & 0x3FFF) + 1, (bits >> 14 & 0x3FFF) + 1))
+        }
+        b"VP8X" => Some((le(&h[24..27]) + 1, le(&h[27..30]) + 1)),
+        _ => None,
+    }
+}
+
+// Skips from segment to segment until the start of frame, which holds the size
+fn jpeg<R: Read + Seek>(file: &mut R) -> Option<(u64, u64)> {
+    loop {
+        let mut marker = [0; 2];
+        file.read_exact(&mut marker).ok()?;
+        if marker[0] != 0xFF {
+            return None;
+        }
+        // Markers may be padded with any number of 0xFF bytes
+        while marker[1] == 0xFF {
+            file.read_exact(&mut marker[1..]).ok()?;
+        }
+
+        // Restart markers, the start of image and TEM have no length
+        if matches!(marker[1], 0xD0..=0xD8 | 0x01) {
+            continue;
+        }
+
+        let mut len = [0; 2];
+        file.read_exact(&mut len).ok()?;
+        let len = be(&len);
+        if len < 2 {
+            return None;
+        }
+
+        // Every SOFn marker, leaving out DHT, JPG and DAC which share the range
+        if matches!(marker[1], 0xC0..=0xCF) && !matches!(marker[1], 0xC4 | 0xC8 | 0xCC) {
+            let mut sof = [0; 5];
+            file.read_exact(&mut sof).ok()?;
+            return Some((be(&sof[3..5]), be(&sof[1..3])));
+        }
+
+        file.seek(SeekFrom::Current(len as i64 - 2)).ok()?;
+    }
+}
+
+/// Running time of a WAV, FLAC, Ogg, MP3, MP4/QuickTime or Matroska/WebM file
+pub fn duration(path: &Path) -> Option<Duration> {
+    read_duration(&mut File::open(path).ok()?)
+}
+
+fn read_duration<R: Read + Seek>(file: &mut R) -> Option<Duration> {
+    let mut h = [0; 12];
+    file.read_exact(&mut h).ok()?;
+    file.rewind().ok()?;
+
+    if h.starts_with(b"RIFF") && &h[8..12] == b"WAVE" {
+        wav(file)
+    } else if h.starts_with(b"fLaC") {
+        flac(file)
+    } else if h.starts_with(b"OggS") {
+        ogg(file)
+    } else if matches!(&h[4..8], b"ftyp" | b"moov" | b"wide" | b"free" | b"mdat") {
+        mp4(file)
+    } else if h.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        matroska(file)
+    } else if h.starts_with(b"ID3") || h[0] == 0xFF && h[1] & 0xE0 == 0xE0 {
+        mp3(file)
+    } else {
+        None
+    }
+}
+
+// The data chunk's size divided by the byte rate from the fmt chunk
+fn wav<R: Read + Seek>(file: &mut R) -> Option<Duration> {
+    file.seek(SeekFrom::Start(12)).ok()?;
+    let mut byte_rate = 0;
+
+    loop {
+        let mut chunk = [0; 8];
+        file.read_exact(&mut chunk).ok()?;
+        let size = le(&chunk[4..8]);
+
+        match &chunk[..4] {
+            b"data" => return seconds(size, byte_rate),
+            b"fmt " => {
+                let mut fmt = [0; 12];
+                file.read_exact(&mut fmt).ok()?;
+                byte_rate = le(&fmt[8..12]);
+                file.seek(SeekFrom::Current(size as i64 - 12)).ok()?;
+            }
+            _ => {
+                file.seek(SeekFrom::Current(size as i64)).ok()?;
+            }
+        }
+
+        // Chunks are padded to an even length
+        if size % 2 == 1 {
+            file.seek(SeekFrom::Current(1)).ok()?;
+        }
+    }
+}
+
+// The STREAMINFO block, which always comes first, has the sample rate and total samples
+fn flac<R: Read + Seek>(file: &mut R) -> Option<Duration> {
+    let mut block = [0; 4 + 4 + 18];
+    file.read_exact(&mut block).ok()?;
+    if block[4] & 0x7F != 0 {
+        return None;
+    }
+
+    let info = &block[8..];
+    let rate = be(&info[10..13]) >> 4;
+    let samples = be(&info[13..18]) & 0xF_FFFF_FFFF;
+    if samples == 0 {
+        return None;
+    }
+    seconds(samples, rate)
+}
+
+// The granule position of the last page is the number of samples, at the rate from the first
+fn ogg<R: Read + Seek>(file: &mut R) -> Option<Duration> {
+    let mut first = [0; 64];
+    let n = file.read(&mut first).ok()?;
+    let segments = *first.get(26)? as usize;
+    let packet = first.get(27 + segments..n)?;
+
+    let (rate, pre_skip) = if packet.starts_with(b"\x01vorbis") {
+        (le(packet.get(12..16)?), 0)
+    } else if packet.starts_with(b"OpusHead") {
+        // Opus always counts samples at 48 kHz
+        (48_000, le(packet.get(10..12)?))
+    } else {
+        return None;
+    };
+
+    let len = file.seek(SeekFrom::End(0)).ok()?;
+    let tail_start = len.saturating_sub(64 * 1024);
+    file.seek(SeekFrom::Start(tail_start)).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+
+    let last = tail.windows(4).rposition(|w| w == b"OggS")?;
+    let granule = le(tail.get(last + 6..last + 14)?);
+    // A granule position of -1 means no packet finishes on that page
+    if granule == u64::MAX {
+        return None;
+    }
+    seconds(granule.saturating_sub(pre_skip), rate)
+}
+
+// The movie header inside the moov box has the duration in its own time scale
+fn mp4<R: Read + Seek>(file: &mut R) -> Option<Duration> {
+    let len = file.seek(SeekFrom::End(0)).ok()?;
+    let (start, end) = find_box(file, 0, len, b"moov")?;
+    let (start, _) = find_box(file, start, end, b"mvhd")?;
+
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut h = [0; 32];
+    file.read_exact(&mut h[..20]).ok()?;
+    let (scale, duration) = if h[0] == 1 {
+        file.read_exact(&mut h[20..]).ok()?;
+        (be(&h[20..24]), be(&h[24..32]))
+    } else {
+        (be(&h[12..16]), be(&h[16..20]))
+    };
+
+    seconds(duration, scale)
+}
+
+// Finds a box between `start` and `end`, giving the range of its contents
+fn find_box<R: Read + Seek>(
+    file: &mut R,
+    start: u64,
+    end: u64,
+    kind: &[u8; 4],
+) -> Option<(u64, u64)> {
+    let mut pos = start;
+    while pos.checked_add(8)? <= end {
+        file.seek(SeekFrom::Start(pos)).ok()?;
+        let mut h = [0; 16];
+        file.read_exact(&mut h[..8]).ok()?;
+
+        let (size, header) = match be(&h[..4]) {
+            0 => (end - pos, 8),
+            1 => {
+                file.read_exact(&mut h[8..]).ok()?;
+                (be(&h[8..16]), 16)
+            }
+            size => (size, 8),
+        };
+        if size < header {
+            return None;
+        }
+        let next = pos.checked_add(size)?;
+        if &h[4..8] == kind {
+            return Some((pos + header, next));
+        }
+
+        pos = next;
+    }
+
+    None
+}
+
+// A Xing or Info header gives the frame count of a VBR file, otherwise the bitrate of the first
+// frame is assumed to be constant
+fn mp3<R: Read + Seek>(file: &mut R) -> Option<Duration> {
+    let len = file.seek(SeekFrom::End(0)).ok()?;
+    file.rewind().ok()?;
+    let mut id3 = [0; 10];
+    file.read_exact(&mut id3).ok()?;
+
+    let mut offset = 0;
+    if id3.starts_with(b"ID3") {
+        // The tag size is stored in 7 bits per byte
+        let size = id3[6..10]
+            .iter()
+            .fold(0, |a, b| a << 7 | (*b as u64 & 0x7F));
+        let footer = if id3[5] & 0x10 != 0 { 10 } else { 0 };
+        offset = 10 + size + footer;
+    }
+
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut buf = vec![0; 4096];
+    let n = file.read(&mut buf).ok()?;
+    let buf = &buf[..n];
+    let i = buf
+        .windows(2)
+        .position(|w| w[0] == 0xFF && w[1] & 0xE0 == 0xE0)?;
+    let frame = buf.get(i..i + 4)?;
+
+    // 3 is MPEG-1, 2 is MPEG-2 and 0 is MPEG-2.5. Only layer III, stored as 1, is supported.
+    let version = frame[1] >> 3 & 3;
+    let layer = frame[1] >> 1 & 3;
+    let bitrate = (frame[2] >> 4) as usize;
+    let rate = (frame[2] >> 2 & 3) as usize;
+    if version == 1 || layer != 1 || bitrate == 0 || bitrate == 15 || rate == 3 {
+        return None;
+    }
+
+    const MPEG1_BITRATES: [u64; 15] = [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const MPEG2_BITRATES: [u64; 15] =
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    const SAMPLE_RATES: [u64; 3] = [44_100, 48_000, 32_000];
+
+    let mpeg1 = version == 3;
+    let sample_rate = SAMPLE_RATES[rate] >> (3 - version).min(2);
+    let samples_per_frame = if mpeg1 { 1152 } else { 576 };
+    let mono = frame[3] >> 6 == 3;
+    let side_info = match (mpeg1, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+
+    let xing = i + 4 + side_info;
+    if let Some(x) = buf.get(xing..xing + 12)
+        && (x.starts_with(b"Xing") || x.starts_with(b"Info"))
+        && x[7] & 1 != 0
+    {
+        return seconds(be(&x[8..12]) * samples_per_frame, sample_rate);
+    }
+
+    let bitrate = if mpeg1 {
+        MPEG1_BITRATES[bitrate]
+    } else {
+        MPEG2_BITRATES[bitrate]
+    };
+    let audio = len.saturating_sub(offset + i as u64);
+    seconds(audio.saturating_mul(8), bitrate * 1000)
+}
+
+const EBML_SEGMENT: u64 = 0x1853_8067;
+const EBML_INFO: u64 = 0x1549_A966;
+const EBML_CLUSTER: u64 = 0x1F43_B675;
+const EBML_TIMESTAMP_SCALE: u64 = 0x2A_D7B1;
+const EBML_DURATION: u64 = 0x4489;
+
+// The segment's Info element has the duration, in units of its timestamp scale
+fn matroska<R: Read + Seek>(file: &mut R) -> Option<Duration> {
+    let mut reader = BufReader::new(file);
+
+    // Skip the EBML header to get to the segment
+    let (_, size) = element(&mut reader)?;
+    reader.seek_relative(size as i64).ok()?;
+    let (id, _) = element(&mut reader)?;
+    if id != EBML_SEGMENT {
+        return None;
+    }
+
+    loop {
+        let (id, size) = element(&mut reader)?;
+        match id {
+            EBML_INFO => return matroska_info(&mut reader, size),
+            // Info always comes before the media data
+            EBML_CLUSTER => return None,
+            _ => reader.seek_relative(size as i64).ok()?,
+        }
+    }
+}
+
+fn matroska_info<R: Read + Seek>(reader: &mut R, size: u64) -> Option<Duration> {
+    let mut scale = 1_000_000;
+    let mut duration = None;
+    let mut read = 0;
+
+    while read < size {
+        let start = reader.stream_position().ok()?;
+        let (id, len) = element(reader)?;
+        if matches!(id, EBML_TIMESTAMP_SCALE | EBML_DURATION) && len <= 8 {
+            let mut data = vec![0; len as usize];
+            reader.read_exact(&mut data).ok()?;
+            match (id, data.len()) {
+                (EBML_TIMESTAMP_SCALE, _) => scale = be(&data),
+                (EBML_DURATION, 4) => {
+                    duration = Some(f32::from_be_bytes(data.try_into().ok()?) as f64)
+                }
+                (EBML_DURATION, 8) => duration = Some(f64::from_be_bytes(data.try_into().ok()?)),
+                _ => {}
+            }
+        } else {
+            reader.seek(SeekFrom::Current(len as i64)).ok()?;
+        }
+        read += reader.stream_position().ok()? - start;
+    }
+
+    let nanos = duration? * scale as f64;
+    Duration::try_from_secs_f64(nanos / 1e9).ok()
+}
+
+// An element's ID, which keeps its length marker, and the size of its data, which doesn't
+fn element<R: Read>(reader: &mut R) -> Option<(u64, u64)> {
+    let id = vint(reader, true)?;
+    let size = vint(reader, false)?;
+    Some((id, size))
+}
+
+fn vint<R: Read>(reader: &mut R, keep_marker: bool) -> Option<u64> {
+    let mut byte = [0; 1];
+    reader.read_exact(&mut byte).ok()?;
+    let len = byte[0].leading_zeros() + 1;
+    if len > 8 {
+        return None;
+    }
+
+    let mut value = if keep_marker {
+        byte[0] as u64
+    } else {
+        byte[0] as u64 & (0xFF >> len)
+    };
+    for _ in 1..len {
+        reader.read_exact(&mut byte).ok()?;
+        value = value << 8 | byte[0] as u64;
+    }
+
+    Some(value)
+}
+
+fn be(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |a, b| a << 8 | *b as u64)
+}
+
+fn le(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |a, b| a << 8 | *b as u64)
+}
+
+// None when the rate is zero or the result is too long to represent, as it can be in a corrupt file
+fn seconds(amount: u64, per_second: u64) -> Option<Duration> {
+    if per_second == 0 {
+        return None;
+    }
+    Duration::try_from_secs_f64(amount as f64 / per_second as f64).ok()
+}
+
+// Like a media player, as m:ss or h:mm:ss
+fn format_duration(duration: Duration) -> String {
+    let total = duration.as_secs_f64().round() as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    // A single Ogg page holding the start of a Vorbis stream
+    fn ogg_page(granule: u64, rate: u32) -> Vec<u8> {
+        let mut packet = b"\x01vorbis".to_vec();
+        packet.extend([0; 4]);
+        packet.push(2);
+        packet.extend(rate.to_le_bytes());
+
+        let mut page = b"OggS\x00\x02".to_vec();
+        page.extend(granule.to_le_bytes());
+        page.extend([0; 12]);
+        page.extend([1, packet.len() as u8]);
+        page.extend(packet);
+        page
+    }
+
+    // An ftyp box followed by a moov box holding only a movie header
+    fn mp4(version: u8, scale: u32, duration: u64) -> Vec<u8> {
+        let mut mvhd = vec![version, 0, 0, 0];
+        if version == 1 {
+            mvhd.extend([0; 16]);
+            mvhd.extend(scale.to_be_bytes());
+            mvhd.extend(duration.to_be_bytes());
+        } else {
+            mvhd.extend([0; 8]);
+            mvhd.extend(scale.to_be_bytes());
+            mvhd.extend((duration as u32).to_be_bytes());
+        }
+
+        let mut file = vec![0, 0, 0, 16];
+        file.extend(b"ftypisom\0\0\0\0");
+        file.extend((mvhd.len() as u32 + 16).to_be_bytes());
+        file.extend(b"moov");
+        file.extend((mvhd.len() as u32 + 8).to_be_bytes());
+        file.extend(b"mvhd");
+        file.extend(mvhd);
+        file
+    }
+
+    #[test]
+    fn png_and_gif_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(640u32.to_be_bytes());
+        png.extend(480u32.to_be_bytes());
+        png.resize(30, 0);
+        assert_eq!(read_dimensions(&mut Cursor::new(png)), Some((640, 480)));
+
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend(320u16.to_le_bytes());
+        gif.extend(200u16.to_le_bytes());
+        gif.resize(30, 0);
+        assert_eq!(read_dimensions(&mut Cursor::new(gif)), Some((320, 200)));
+    }
+
+    #[test]
+    fn jpeg_dimensions_skip_other_segments() {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 16];
+        jpeg.extend(b"JFIF\0");
+        jpeg.extend([0; 9]);
+        jpeg.extend([0xFF, 0xC0, 0, 17, 8]);
+        jpeg.extend(1080u16.to_be_bytes());
+        jpeg.extend(1920u16.to_be_bytes());
+        jpeg.resize(64, 0);
+        assert_eq!(read_dimensions(&mut Cursor::new(jpeg)), Some((1920, 1080)));
+    }
+
+    #[test]
+    fn webp_extended_dimensions() {
+        let mut h = [0; 30];
+        h[..16].copy_from_slice(b"RIFF\0\0\0\0WEBPVP8X");
+        h[24..27].copy_from_slice(&[0x1F, 0x03, 0x00]);
+        h[27..30].copy_from_slice(&[0x57, 0x02, 0x00]);
+        assert_eq!(webp(&h), Some((800, 600)));
+    }
+
+    #[test]
+    fn ogg_duration() {
+        let page = ogg_page(44_100 * 75, 44_100);
+        assert_eq!(ogg(&mut Cursor::new(page)), Some(Duration::from_secs(75)));
+
+        // An unset granule position and a zero rate are both unknown rather than a panic
+        assert_eq!(ogg(&mut Cursor::new(ogg_page(u64::MAX, 1))), None);
+        assert_eq!(ogg(&mut Cursor::new(ogg_page(1000, 0))), None);
+    }
+
+    #[test]
+    fn mp4_duration() {
+        let file = mp4(0, 600, 600 * 3725);
+        assert_eq!(
+            read_duration(&mut Cursor::new(file)),
+            Some(Duration::from_secs(3725))
+        );
+
+        let file = mp4(1, 1, u64::MAX);
+        assert_eq!(read_duration(&mut Cursor::new(file)), None);
+    }
+
+    #[test]
+    fn find_box_rejects_overflowing_sizes() {
+        let mut file = vec![0, 0, 0, 8];
+        file.extend(b"free");
+        file.extend([0, 0, 0, 1]);
+        file.extend(b"skip");
+        file.extend(u64::MAX.to_be_bytes());
+        let len = file.len() as u64;
+        assert_eq!(find_box(&mut Cursor::new(file), 0, len, b"moov"), None);
+    }
+
+    #[test]
+    fn seconds_out_of_range() {
+        assert_eq!(seconds(90, 60), Some(Duration::from_millis(1500)));
+        assert_eq!(seconds(1, 0), None);
+        assert_eq!(seconds(u64::MAX, 1), None);
+    }
+
+    #[test]
+    fn durations_format_like_a_player() {
+        assert_eq!(format_duration(Duration::from_secs(5)), "0:05");
+        assert_eq!(format_duration(Duration::from_secs(200)), "3:20");
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1:02:05");
+    }
+}
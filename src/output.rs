@@ -1,35 +1,64 @@
+mod diff;
 mod entry;
+mod grid;
 mod long;
 mod short;
+mod summary;
+mod tree;
 
-use std::fmt::Display;
+use std::{fmt::Display, ops::Range};
 
-use crossterm::style::StyledContent;
+use crate::{
+    config,
+    files::FsEntry,
+    output::{entry::header, summary::summary},
+    sorting::sort,
+};
+
+use clap::ValueEnum;
+use crossterm::style::{StyledContent, Stylize};
+pub use diff::{print_diff, print_diff_json};
+pub use grid::GridDirection;
 pub use long::long;
+use serde::{Deserialize, Serialize};
 pub use short::short;
+pub use tree::tree;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// The format entries are listed in, chosen by subcommand or the `display.mode` config key
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    #[default]
+    Short,
+    Long,
+    Tree,
+    Table,
+}
 
+#[derive(Clone)]
 pub struct MultiStyled<D>
 where
     D: Display,
 {
     sections: Vec<StyledContent<D>>,
+    // Ranges of sections wrapped in an OSC 8 hyperlink to the given URL
+    links: Vec<(Range<usize>, String)>,
 }
 
 impl<D: Display> From<StyledContent<D>> for MultiStyled<D> {
     fn from(value: StyledContent<D>) -> Self {
         Self {
             sections: vec![value],
+            links: Vec::new(),
         }
     }
 }
 
 impl<D: Display> Display for MultiStyled<D> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for sec in &self.sections {
-            f.write_str(&sec.to_string())?;
-        }
-
-        Ok(())
+        f.write_str(&self.render(true))
     }
 }
 
@@ -37,43 +66,146 @@ impl<D: Display> MultiStyled<D> {
     pub fn new() -> Self {
         Self {
             sections: Vec::new(),
+            links: Vec::new(),
         }
     }
 
-    pub fn with(mut self, content: StyledContent<D>) -> Self {
-        self.sections.push(content);
-        self
-    }
-
     pub fn push(&mut self, content: StyledContent<D>) {
         self.sections.push(content);
     }
 
     pub fn insert(&mut self, index: usize, content: StyledContent<D>) {
         self.sections.insert(index, content);
+        for (range, _) in &mut self.links {
+            if range.start >= index {
+                *range = range.start + 1..range.end + 1;
+            }
+        }
     }
 
     pub fn append(&mut self, mut content: MultiStyled<D>) {
+        let offset = self.sections.len();
         self.sections.append(&mut content.sections);
+        self.links.extend(
+            content
+                .links
+                .into_iter()
+                .map(|(range, url)| (range.start + offset..range.end + offset, url)),
+        );
     }
 
-    pub fn section(&self, index: usize) -> Option<&StyledContent<D>> {
-        if index < self.sections.len() {
-            return Some(&self.sections[index]);
-        }
+    /// Wraps every current section in a hyperlink to `url`
+    pub fn link(&mut self, url: String) {
+        self.links.push((0..self.sections.len(), url));
+    }
 
-        return None;
+    pub fn output(&self, display: &config::Display) -> String {
+        self.render(display.color_enabled)
     }
 
-    pub fn output(&self) -> String {
-        format!("{self}")
+    fn render(&self, styled: bool) -> String {
+        let mut output = String::new();
+        for (i, sec) in self.sections.iter().enumerate() {
+            if let Some((_, url)) = self.links.iter().find(|(r, _)| r.start == i) {
+                output.push_str(&format!("\x1b]8;;{url}\x1b\\"));
+            }
+
+            if styled {
+                output.push_str(&sec.to_string());
+            } else {
+                output.push_str(&sec.content().to_string());
+            }
+
+            if self.links.iter().any(|(r, _)| r.end == i + 1) {
+                output.push_str("\x1b]8;;\x1b\\");
+            }
+        }
+
+        output
     }
 }
 
 impl<S: AsRef<str> + Display> MultiStyled<S> {
-    pub fn len(&self) -> usize {
+    pub fn width(&self) -> usize {
         self.sections
             .iter()
-            .fold(0, |a, s| a + s.content().as_ref().len())
+            .fold(0, |a, s| a + display_width(s.content().as_ref()))
+    }
+}
+
+/// Prints the roots in the configured output mode
+pub fn print(roots: &[FsEntry], config: &config::Config) {
+    match config.display.mode {
+        OutputMode::Tree => tree(roots, config),
+        OutputMode::Long => long(roots, config),
+        _ => short(roots, config),
     }
 }
+
+// Prints the listing of each root, followed by each of its subdirectories when recursing
+pub fn print_sections<F>(roots: &[FsEntry], config: &config::Config, render: F)
+where
+    F: Fn(&FsEntry) -> String,
+{
+    // A flat listing already contains the whole subtree, so there are no sections to recurse into
+    let recurse = config.filter.recurse && !config.filter.flat;
+    let headers = roots.len() > 1 || recurse;
+
+    for (i, root) in roots.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        if headers {
+            println!("{}", header(root, root, &config.display));
+        }
+        print_listing(root, config, &render);
+
+        if recurse {
+            print_subdirs(root, root, config, &render);
+        }
+    }
+}
+
+fn print_listing<F>(dir: &FsEntry, config: &config::Config, render: &F)
+where
+    F: Fn(&FsEntry) -> String,
+{
+    println!("{}", render(dir));
+    if config.display.summary
+        && let Some(summary) = summary(dir, &config.display)
+    {
+        println!("{summary}");
+    }
+}
+
+// Depth-first, in the same order as the entries are listed
+fn print_subdirs<F>(dir: &FsEntry, root: &FsEntry, config: &config::Config, render: &F)
+where
+    F: Fn(&FsEntry) -> String,
+{
+    let Some(children) = dir.children.as_ref() else {
+        return;
+    };
+
+    for child in sort(children, config.sorting.mode, config.sorting.reverse) {
+        if child.children.is_some() {
+            println!();
+            println!("{}", header(&child, root, &config.display));
+            print_listing(&child, config, render);
+            print_subdirs(&child, root, config, render);
+        }
+    }
+}
+
+pub fn pad_right(input: &mut MultiStyled<String>, length: usize) {
+    let c_length = input.width();
+    if length > c_length {
+        input.push(" ".repeat(length - c_length).stylize())
+    }
+}
+
+// Terminal columns taken up by a string, measured per grapheme so that emoji ZWJ sequences
+// and combining marks count as a single cell, and East Asian wide characters as two
+pub fn display_width(s: &str) -> usize {
+    s.graphemes(true).map(|g| g.width()).sum()
+}
@@ -0,0 +1,103 @@
+use std::{borrow::Cow, io, os::unix::ffi::OsStrExt, path::Path};
+
+use crossterm::style::Stylize;
+use serde::Serialize;
+
+use crate::{
+    config::Config,
+    diff::{Change, Difference, Status},
+    files::FsEntry,
+    output::{MultiStyled, entry::display_name},
+    style::ls_style,
+    util::human_size,
+};
+
+// Paths are written as strings, with the raw bytes alongside any which aren't valid UTF-8 so
+// that they can still be told apart
+#[derive(Serialize)]
+struct Report<'a> {
+    left: Cow<'a, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    left_bytes: Option<&'a [u8]>,
+    right: Cow<'a, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    right_bytes: Option<&'a [u8]>,
+    entries: Vec<Entry<'a>>,
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    path: Cow<'a, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<&'a [u8]>,
+    status: Status,
+    #[serde(skip_serializing_if = "<[Change]>::is_empty")]
+    changes: &'a [Change],
+}
+
+fn raw_bytes(path: &Path) -> Option<&[u8]> {
+    path.to_str().is_none().then(|| path.as_os_str().as_bytes())
+}
+
+/// Prints one line per difference, marked `-` when only on the left, `+` when only on the right
+/// and `~` when modified, followed by what changed
+pub fn print_diff(differences: &[Difference], config: &Config) {
+    let style = ls_style();
+    for d in differences {
+        let marker = match d.status {
+            Status::Removed => "-".to_string().red(),
+            Status::Added => "+".to_string().green(),
+            Status::Modified => "~".to_string().yellow(),
+            Status::Identical => "=".to_string().dim(),
+        };
+
+        // Listed by its path relative to the roots, like a flat listing
+        let mut entry = FsEntry::clone(&d.entry);
+        entry.raw_name = d.path.clone().into_os_string();
+
+        let mut line: MultiStyled<String> = marker.into();
+        line.push(" ".to_string().stylize());
+        line.append(display_name(&entry, &style, &config.display));
+        if !d.changes.is_empty() {
+            let changes: Vec<String> = d.changes.iter().map(describe).collect();
+            line.push(format!("  {}", changes.join(", ")).dim());
+        }
+
+        println!("{}", line.output(&config.display));
+    }
+}
+
+pub fn print_diff_json(differences: &[Difference], left: &Path, right: &Path) -> io::Result<()> {
+    let report = Report {
+        left: left.to_string_lossy(),
+        left_bytes: raw_bytes(left),
+        right: right.to_string_lossy(),
+        right_bytes: raw_bytes(right),
+        entries: differences
+            .iter()
+            .map(|d| Entry {
+                path: d.path.to_string_lossy(),
+                bytes: raw_bytes(&d.path),
+                status: d.status,
+                changes: &d.changes,
+            })
+            .collect(),
+    };
+
+    // Serialised up front so that a failure doesn't leave half a document on stdout
+    let json = serde_json::to_string_pretty(&report)?;
+    println!("{json}");
+    Ok(())
+}
+
+fn describe(change: &Change) -> String {
+    match change {
+        Change::Type { left, right } => format!("type {left} → {right}"),
+        Change::Size { left, right } => {
+            format!("size {} → {}", human_size(*left), human_size(*right))
+        }
+        Change::Mode { left, right } => format!("mode {left} → {right}"),
+        Change::Modified { left, right } if right > left => "newer".to_string(),
+        Change::Modified { .. } => "older".to_string(),
+    }
+}
@@ -1,23 +1,40 @@
+use std::fs;
+
 use crossterm::style::Stylize;
+use pathdiff::diff_paths;
 
 use crate::{
+    config::Display,
     files::{EntryType, FileType, FsEntry},
     output::MultiStyled,
+    quoting::quote,
     style::LilsStyle,
+    util::file_url,
 };
 
-pub fn display_name(
-    entry: &FsEntry,
-    style: &LilsStyle,
-    suffix: bool,
-    icons: bool,
-) -> MultiStyled<String> {
-    let applied = style.apply(entry);
+pub fn display_name(entry: &FsEntry, style: &LilsStyle, display: &Display) -> MultiStyled<String> {
+    let mut applied = style.apply(entry, quoted_name(entry, display));
+    if display.highlighted.contains(&entry.path) {
+        applied = applied.reverse();
+    }
     let mut multi: MultiStyled<String> = applied.into();
-    if suffix && let Some(suffix) = get_suffix(entry) {
+    if display.suffix
+        && let Some(suffix) = get_suffix(entry)
+    {
         multi.push(suffix.to_string().stylize());
     }
-    if icons && let Some(icon_raw) = get_icon(entry) {
+    if entry.mount_point {
+        multi.push(" [mount]".to_string().dim());
+    }
+    if display.hyperlinks
+        && display.tty
+        && let Some(url) = file_url(&entry.path)
+    {
+        multi.link(url);
+    }
+    if display.icons
+        && let Some(icon_raw) = get_icon(entry)
+    {
         let icon = format!("{icon_raw} ");
         multi.insert(0, icon.stylize());
     }
@@ -25,14 +42,37 @@ pub fn display_name(
     multi
 }
 
+pub fn quoted_name(entry: &FsEntry, display: &Display) -> String {
+    quote(&entry.raw_name, display.quoting, display.tty)
+}
+
+// Underlined directory path printed above each listing when there are several. Like `ls -R`,
+// the root argument is shown as given and nested directories are prefixed with it, so that
+// directories of the same name under different roots can be told apart.
+pub fn header(entry: &FsEntry, root: &FsEntry, display: &Display) -> String {
+    let path = if display.absolute_paths {
+        fs::canonicalize(&entry.path).unwrap_or_else(|_| entry.path.clone())
+    } else {
+        diff_paths(&entry.path, &root.path)
+            .filter(|p| !p.as_os_str().is_empty())
+            .map_or_else(|| root.path.clone(), |p| root.path.join(p))
+    };
+
+    let name: MultiStyled<String> = quote(path.as_os_str(), display.quoting, display.tty)
+        .stylize()
+        .underlined()
+        .bold()
+        .into();
+    format!("{}:", name.output(display))
+}
+
 pub fn get_suffix(entry: &FsEntry) -> Option<char> {
     match entry.e_type {
         EntryType::Directory => Some('/'),
         EntryType::Socket => Some('='),
-        EntryType::File(e_type) => match e_type {
-            FileType::Executable => Some('*'),
-            _ => None,
-        },
+        EntryType::Fifo => Some('|'),
+        EntryType::Symlink => Some('@'),
+        EntryType::File(FileType::Executable) => Some('*'),
         _ => None,
     }
 }
@@ -43,7 +83,9 @@ pub fn get_icon(entry: &FsEntry) -> Option<char> {
         EntryType::File(ft) => match ft {
             FileType::Executable => Some(''),
             FileType::Text => Some('󰈚'),
-            _ => None,
+            FileType::Image => Some(''),
+            FileType::Audio => Some(''),
+            FileType::Video => Some(''),
         },
         _ => None,
     }
@@ -0,0 +1,96 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// The order in which cells are placed into a grid, equivalent to `ls -C` and `ls -x`
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GridDirection {
+    #[default]
+    Down,
+    Across,
+}
+
+/// A column layout for a list of cells, using the minimal number of rows which fits the width
+#[derive(Debug, Clone)]
+pub struct Grid {
+    pub rows: usize,
+    pub cols: usize,
+    pub widths: Vec<usize>,
+    count: usize,
+    direction: GridDirection,
+}
+
+impl Grid {
+    pub fn fit(cells: &[usize], separator: usize, width: usize, direction: GridDirection) -> Self {
+        let count = cells.len();
+        if count == 0 {
+            return Self {
+                rows: 0,
+                cols: 0,
+                widths: Vec::new(),
+                count,
+                direction,
+            };
+        }
+
+        // No column can be narrower than the narrowest cell, which bounds how many can fit
+        let narrowest = cells.iter().copied().min().unwrap_or(1).max(1);
+        let max_cols = ((width + separator) / (narrowest + separator)).clamp(1, count);
+
+        for cols in (1..=max_cols).rev() {
+            let grid = Self::with_cols(cells, cols, direction);
+            if grid.cols == 1 || grid.total_width(separator) <= width {
+                return grid;
+            }
+        }
+
+        Self::with_cols(cells, 1, direction)
+    }
+
+    fn with_cols(cells: &[usize], cols: usize, direction: GridDirection) -> Self {
+        let count = cells.len();
+        let rows = count.div_ceil(cols);
+        // Filling down may leave trailing columns empty, so recompute the real column count
+        let cols = match direction {
+            GridDirection::Down => count.div_ceil(rows),
+            GridDirection::Across => cols,
+        };
+
+        let mut grid = Self {
+            rows,
+            cols,
+            widths: vec![0; cols],
+            count,
+            direction,
+        };
+
+        for (i, w) in cells.iter().enumerate() {
+            let (_, col) = grid.position(i);
+            grid.widths[col] = grid.widths[col].max(*w);
+        }
+
+        grid
+    }
+
+    fn total_width(&self, separator: usize) -> usize {
+        self.widths.iter().sum::<usize>() + separator * (self.cols - 1)
+    }
+
+    /// The (row, column) position of the cell at `index`
+    pub fn position(&self, index: usize) -> (usize, usize) {
+        match self.direction {
+            GridDirection::Down => (index % self.rows, index / self.rows),
+            GridDirection::Across => (index / self.cols, index % self.cols),
+        }
+    }
+
+    /// The index of the cell at (row, column), if there is one
+    pub fn index(&self, row: usize, col: usize) -> Option<usize> {
+        let index = match self.direction {
+            GridDirection::Down => col * self.rows + row,
+            GridDirection::Across => row * self.cols + col,
+        };
+
+        (index < self.count).then_some(index)
+    }
+}
@@ -1,66 +1,181 @@
-use std::os::unix::fs::PermissionsExt;
+use std::{os::unix::fs::PermissionsExt, path::Path, rc::Rc};
 
-use crossterm::{style::Stylize, terminal::size};
+use crossterm::style::Stylize;
 use nix::sys::stat::Mode;
 
 use crate::{
-    config::Config,
+    config::{Config, Display},
     files::{EntryType, FileType, FsEntry},
-    output::MultiStyled,
+    hash::{HashAlgorithm, hash_files},
+    lines::count_lines,
+    media::media_info,
+    output::{
+        MultiStyled,
+        entry::{display_name, quoted_name},
+        pad_right, print_sections,
+    },
     sorting::sort,
     style::{LilsStyle, ls_style},
+    util::par_map,
 };
 
 pub fn long(roots: &[FsEntry], config: &Config) {
-    if config.filter.recurse {
-        let all = roots.iter().map(|e| e.get_all_dirs());
-
-        all.for_each(|r| {
-            r.iter().enumerate().for_each(|(i, e)| {
-                display_single(e, config, r.len(), i);
-            });
-        });
-    } else {
-        roots.iter().enumerate().for_each(|(i, e)| {
-            display_single(e, config, roots.len(), i);
-        });
-    }
+    print_sections(roots, config, |e| long_display(e, config));
 }
 
-fn display_single(entry: &FsEntry, config: &Config, len: usize, idx: usize) {
-    let output = long_display(entry, config);
-    if len > 1 {
-        println!("{}:", entry.name.clone().stylize().underlined().bold());
+fn long_display(root: &FsEntry, config: &Config) -> String {
+    let style = ls_style();
+    if root.children.is_none() {
+        return quoted_name(root, &config.display);
     }
-    println!("{output}");
-    if idx < len - 1 {
-        println!();
+
+    let children = root.children.clone().unwrap();
+    let files = sort(&children, config.sorting.mode, config.sorting.reverse);
+
+    let mut lines = line_column(&files, &config.display).into_iter();
+    let mut media = media_column(&files, &config.display).into_iter();
+    let mut hashes = hash_column(&files, &config.display).into_iter();
+
+    let rows: Vec<Vec<MultiStyled<String>>> = files
+        .iter()
+        .map(|f| {
+            let mut row = vec![
+                get_permission_string(f, &style),
+                f.u_name
+                    .as_ref()
+                    .cloned()
+                    .unwrap_or_default()
+                    .stylize()
+                    .into(),
+                f.g_name
+                    .as_ref()
+                    .cloned()
+                    .unwrap_or_default()
+                    .stylize()
+                    .into(),
+            ];
+            if let Some(count) = lines.next() {
+                row.push(count);
+            }
+            if let Some(info) = media.next() {
+                row.push(info);
+            }
+            if let Some(hash) = hashes.next() {
+                row.push(hash);
+            }
+            row.push(display_name(f, &style, &config.display));
+            row
+        })
+        .collect();
+
+    align_columns(rows, &config.display).join("\n")
+}
+
+// Line counts of text files, right aligned, or empty when they weren't asked for
+fn line_column(files: &[Rc<FsEntry>], display: &Display) -> Vec<MultiStyled<String>> {
+    if !display.lines {
+        return Vec::new();
     }
+
+    // Only regular files classed as text are opened, so FIFOs and devices are never read from.
+    // Binary files among them are caught while counting.
+    let text: Vec<Option<&Path>> = files
+        .iter()
+        .map(|f| (f.e_type == EntryType::File(FileType::Text)).then_some(f.path.as_path()))
+        .collect();
+    let counts: Vec<String> = par_map(&text, |p| p.and_then(|p| count_lines(p).ok().flatten()))
+        .into_iter()
+        .map(|c| c.map_or_else(|| "-".to_string(), |c| c.to_string()))
+        .collect();
+    let width = counts.iter().map(|c| c.len()).max().unwrap_or(0);
+
+    counts
+        .into_iter()
+        .map(|c| format!("{c:>width$}").stylize().into())
+        .collect()
 }
 
-fn long_display(root: &FsEntry, config: &Config) -> String {
-    let style = ls_style();
-    if root.children.is_none() {
-        return root.name.clone();
+// Image dimensions and audio or video durations, right aligned, or empty when not asked for
+fn media_column(files: &[Rc<FsEntry>], display: &Display) -> Vec<MultiStyled<String>> {
+    if !display.media {
+        return Vec::new();
     }
 
-    let mut lines: Vec<String> = Vec::new();
+    let media: Vec<Option<(&Path, FileType)>> = files
+        .iter()
+        .map(|f| match f.e_type {
+            EntryType::File(ft) => Some((f.path.as_path(), ft)),
+            _ => None,
+        })
+        .collect();
+    let info: Vec<String> = par_map(&media, |m| m.and_then(|(p, ft)| media_info(p, ft)))
+        .into_iter()
+        .map(|i| i.unwrap_or_else(|| "-".to_string()))
+        .collect();
+    let width = info.iter().map(|i| i.len()).max().unwrap_or(0);
 
-    let children = root.children.clone().unwrap();
-    let (width, _) = size().unwrap_or((160, 0));
+    info.into_iter()
+        .map(|i| format!("{i:>width$}").stylize().into())
+        .collect()
+}
 
-    let files = sort(&children, config.sorting.mode, config.sorting.reverse);
+// Hashes of the regular files in the listing, computed up front so they can be done in parallel.
+// Empty when no hash was asked for, so that there's no column at all.
+fn hash_column(files: &[Rc<FsEntry>], display: &Display) -> Vec<MultiStyled<String>> {
+    if display.hash == HashAlgorithm::None {
+        return Vec::new();
+    }
+
+    let regular: Vec<&Path> = files
+        .iter()
+        .filter(|f| matches!(f.e_type, EntryType::File(_)))
+        .map(|f| f.path.as_path())
+        .collect();
+    let mut hashes = hash_files(&regular, display.hash).into_iter();
 
-    for f in &files {
-        let mut output: MultiStyled<String> = MultiStyled::new();
-        output.append(get_permission_string(f, &style));
-        output.push(f.u_name.as_ref().cloned().unwrap_or_default().stylize());
-        output.push(f.g_name.as_ref().cloned().unwrap_or_default().stylize());
+    files
+        .iter()
+        .map(|f| {
+            let hash = if matches!(f.e_type, EntryType::File(_)) {
+                hashes.next().flatten()
+            } else {
+                None
+            };
+            let mut hash = hash.unwrap_or_else(|| "-".to_string());
+            // A width of 0 shows the whole hash
+            if display.hash_width > 0 {
+                hash.truncate(display.hash_width);
+            }
+            hash.dim().into()
+        })
+        .collect()
+}
 
-        lines.push(output.output());
+// Pads every column but the last to the display width of its widest cell
+fn align_columns(rows: Vec<Vec<MultiStyled<String>>>, display: &Display) -> Vec<String> {
+    let mut widths: Vec<usize> = Vec::new();
+    for row in &rows {
+        for (i, cell) in row.iter().enumerate() {
+            if i >= widths.len() {
+                widths.push(0);
+            }
+            widths[i] = widths[i].max(cell.width());
+        }
     }
 
-    lines.join("\n")
+    rows.into_iter()
+        .map(|row| {
+            let len = row.len();
+            let mut line: MultiStyled<String> = MultiStyled::new();
+            for (i, mut cell) in row.into_iter().enumerate() {
+                if i < len - 1 {
+                    pad_right(&mut cell, widths[i] + 1);
+                }
+                line.append(cell);
+            }
+            line.output(display)
+        })
+        .collect()
 }
 
 fn get_permission_string(entry: &FsEntry, style: &LilsStyle) -> MultiStyled<String> {
@@ -73,11 +188,12 @@ fn get_permission_string(entry: &FsEntry, style: &LilsStyle) -> MultiStyled<Stri
         EntryType::BlockDevice => 'b',
         EntryType::CharDevice => 'c',
         EntryType::Socket => 's',
+        EntryType::Fifo => 'p',
         EntryType::Symlink => 'l',
     };
 
-    let perms_mode: u16 = entry.perms.mode() as u16;
-    let mode = nix::sys::stat::Mode::from_bits_truncate(perms_mode);
+    let perms_mode = entry.perms.mode() as nix::sys::stat::mode_t;
+    let mode = Mode::from_bits_truncate(perms_mode);
 
     let o_read = mode.contains(Mode::S_IRUSR);
     let o_write = mode.contains(Mode::S_IWUSR);
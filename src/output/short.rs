@@ -1,97 +1,83 @@
-use crossterm::{style::Stylize, terminal::size};
+use std::env;
+
+use crossterm::terminal::size;
 
 use crate::{
-    config::Config,
+    config::{Config, Display},
     files::FsEntry,
-    output::{MultiStyled, entry::display_name},
+    output::{
+        MultiStyled,
+        entry::{display_name, quoted_name},
+        grid::Grid,
+        pad_right, print_sections,
+    },
     sorting::sort,
     style::ls_style,
 };
 
 pub fn short(roots: &[FsEntry], config: &Config) {
-    if config.filter.recurse {
-        let all = roots.iter().map(|e| e.get_all_dirs());
-
-        all.for_each(|r| {
-            r.iter().enumerate().for_each(|(i, e)| {
-                display_single(e, config, r.len(), i);
-            });
-        });
-    } else {
-        roots.iter().enumerate().for_each(|(i, e)| {
-            display_single(e, config, roots.len(), i);
-        });
-    }
-}
-
-fn display_single(entry: &FsEntry, config: &Config, len: usize, index: usize) {
-    let output = short_display(entry, config);
-    if len > 1 {
-        println!("{}:", entry.name.clone().stylize().underlined().bold());
-    }
-    println!("{output}");
-    if index < len - 1 {
-        println!()
-    }
+    print_sections(roots, config, |e| short_display(e, config));
 }
 
 fn short_display(root: &FsEntry, config: &Config) -> String {
     let style = ls_style();
     if root.children.is_none() {
-        return root.name.clone();
+        return quoted_name(root, &config.display);
     }
 
     let children = root.children.clone().unwrap();
+    let separator = if config.display.icons { 3 } else { 2 };
 
-    let (width, _) = size().unwrap_or((160, 0));
-    let mut lines: Vec<String> = Vec::new();
-    let mut longest_len = 1;
-    let longest_file = children.iter().max_by_key(|f| f.name.len());
-
-    if let Some(longest) = longest_file {
-        longest_len = longest.name.len();
-    }
-
-    let files_per = width as usize / longest_len;
-
-    let mut curr = String::new();
-    let mut line_pos = 0;
     let files = sort(&children, config.sorting.mode, config.sorting.reverse);
+    let cells: Vec<MultiStyled<String>> = files
+        .iter()
+        .map(|f| display_name(f, &style, &config.display))
+        .collect();
+    let Some(width) = grid_width(&config.display) else {
+        let lines: Vec<String> = cells.iter().map(|c| c.output(&config.display)).collect();
+        return lines.join("\n");
+    };
+    let widths: Vec<usize> = cells.iter().map(|c| c.width()).collect();
+
+    let grid = Grid::fit(&widths, separator, width, config.display.grid);
+    let mut lines: Vec<String> = Vec::new();
 
-    for f in &files {
-        let mut output: MultiStyled<String> =
-            display_name(f, &style, config.display.suffix, config.display.icons);
-        pad_right(&mut output, longest_len);
-
-        if line_pos >= files_per {
-            line_pos = 0;
-            lines.push(curr);
-            curr = String::new();
-        }
-
-        if line_pos < files_per {
-            let between = if config.display.icons { "  " } else { " " };
-            output.push(between.to_string().stylize())
+    for row in 0..grid.rows {
+        let mut line: MultiStyled<String> = MultiStyled::new();
+        let cols: Vec<usize> = (0..grid.cols)
+            .filter_map(|col| grid.index(row, col))
+            .collect();
+
+        for (col, idx) in cols.iter().enumerate() {
+            let mut cell = cells[*idx].clone();
+            // The last cell of a row doesn't need padding
+            if col < cols.len() - 1 {
+                pad_right(&mut cell, grid.widths[col] + separator);
+            }
+            line.append(cell);
         }
 
-        curr.push_str(&output.output());
-        line_pos += 1;
-    }
-
-    if !curr.is_empty() {
-        lines.push(curr)
+        lines.push(line.output(&config.display));
     }
 
     lines.join("\n")
 }
 
-fn pad_right(input: &mut MultiStyled<String>, length: usize) {
-    let c_length = input.len();
-    let mut pad = String::from("");
-    if length > c_length {
-        let remaining_len = length - c_length;
-        pad = (0..remaining_len).map(|_| " ").collect::<Vec<_>>().concat();
+// Width available to the grid, or None when entries should be printed one per line
+fn grid_width(display: &Display) -> Option<usize> {
+    if display.oneline {
+        return None;
+    }
+    if display.width.is_some() {
+        return display.width;
+    }
+    if !display.tty {
+        return None;
     }
 
-    input.push(pad.stylize())
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .or_else(|| size().ok().map(|(w, _)| w as usize))
+        .or(Some(80))
 }
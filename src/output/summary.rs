@@ -0,0 +1,72 @@
+use crossterm::style::Stylize;
+
+use crate::{
+    config::Display,
+    files::{EntryType, FsEntry, Skipped},
+    output::MultiStyled,
+    util::human_size,
+};
+
+// Footer with counts of what a directory listing contains and what the filters left out
+pub fn summary(entry: &FsEntry, display: &Display) -> Option<String> {
+    let mut totals = Totals::default();
+    for c in entry.children.as_ref()? {
+        totals.add(c);
+    }
+    totals.add_skipped(entry.skipped);
+
+    Some(totals.output(display))
+}
+
+/// Running counts for a summary footer, added to entry by entry
+#[derive(Debug, Default)]
+pub struct Totals {
+    files: usize,
+    dirs: usize,
+    symlinks: usize,
+    size: u64,
+    skipped: Skipped,
+}
+
+impl Totals {
+    pub fn add(&mut self, entry: &FsEntry) {
+        match entry.e_type {
+            EntryType::Directory => self.dirs += 1,
+            EntryType::Symlink => self.symlinks += 1,
+            _ => self.files += 1,
+        }
+        if entry.e_type != EntryType::Directory {
+            self.size += entry.size;
+        }
+    }
+
+    pub fn add_skipped(&mut self, skipped: Skipped) {
+        self.skipped.hidden += skipped.hidden;
+        self.skipped.ignored += skipped.ignored;
+    }
+
+    pub fn output(&self, display: &Display) -> String {
+        let mut parts = vec![count(self.files, "file"), count(self.dirs, "directory")];
+        if self.symlinks > 0 {
+            parts.push(count(self.symlinks, "symlink"));
+        }
+        if self.skipped.hidden > 0 {
+            parts.push(format!("{} hidden", self.skipped.hidden));
+        }
+        if self.skipped.ignored > 0 {
+            parts.push(format!("{} ignored", self.skipped.ignored));
+        }
+        parts.push(human_size(self.size));
+
+        let output: MultiStyled<String> = parts.join(", ").dim().into();
+        output.output(display)
+    }
+}
+
+fn count(n: usize, noun: &str) -> String {
+    match (n, noun) {
+        (1, _) => format!("1 {noun}"),
+        (_, "directory") => format!("{n} directories"),
+        _ => format!("{n} {noun}s"),
+    }
+}
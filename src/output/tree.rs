@@ -0,0 +1,148 @@
+use crossterm::style::Stylize;
+
+use crate::{
+    config::{Config, Display},
+    files::{EntryType, FsEntry},
+    output::{MultiStyled, entry::display_name, summary::Totals},
+    quoting::quote,
+    sorting::sort,
+    style::{LilsStyle, ls_style},
+};
+
+pub fn tree(roots: &[FsEntry], config: &Config) {
+    let style = ls_style();
+    for (i, root) in roots.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+
+        let path = quote(
+            root.path.as_os_str(),
+            config.display.quoting,
+            config.display.tty,
+        );
+        let mut name: MultiStyled<String> = style.apply(root, path.clone()).into();
+        if root.e_type == EntryType::Directory && config.display.suffix && !path.ends_with('/') {
+            name.push("/".to_string().stylize());
+        }
+        println!("{}", name.output(&config.display));
+
+        // The footer counts what was drawn across the whole tree, after pruning and limits
+        let mut lines = Vec::new();
+        let mut totals = Totals::default();
+        totals.add_skipped(root.skipped);
+        tree_lines(root, "", config, &style, &mut lines, &mut totals);
+        for line in lines {
+            println!("{line}");
+        }
+
+        if config.display.summary && root.children.is_some() {
+            println!("{}", totals.output(&config.display));
+        }
+    }
+}
+
+fn tree_lines(
+    dir: &FsEntry,
+    prefix: &str,
+    config: &Config,
+    style: &LilsStyle,
+    lines: &mut Vec<String>,
+    totals: &mut Totals,
+) {
+    let Some(children) = dir.children.as_ref() else {
+        return;
+    };
+
+    let mut files = sort(children, config.sorting.mode, config.sorting.reverse);
+    if config.display.prune {
+        files.retain(|f| !is_prunable(f));
+    }
+
+    // A limit of 0 means every entry is shown
+    let limit = config.display.max_per_dir;
+    let hidden = if limit > 0 && files.len() > limit {
+        files.len() - limit
+    } else {
+        0
+    };
+    files.truncate(files.len() - hidden);
+
+    for (i, file) in files.iter().enumerate() {
+        let last = i == files.len() - 1 && hidden == 0;
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        let (name, chain) = collapsed_name(file, config, style);
+        lines.push(format!("{prefix}{branch}{}", name.output(&config.display)));
+        for entry in &chain {
+            totals.add(entry);
+            totals.add_skipped(entry.skipped);
+        }
+
+        let end = chain.last().copied().unwrap_or(file);
+        tree_lines(
+            end,
+            &format!("{prefix}{indent}"),
+            config,
+            style,
+            lines,
+            totals,
+        );
+    }
+
+    if hidden > 0 {
+        let more: MultiStyled<String> = format!("… and {hidden} more").dim().into();
+        lines.push(format!("{prefix}└── {}", more.output(&config.display)));
+    }
+}
+
+// Joins a chain of directories which each contain only one directory into a single `a/b/c`
+// name, returning it along with every entry of the chain. The children of the last come next.
+fn collapsed_name<'a>(
+    entry: &'a FsEntry,
+    config: &Config,
+    style: &LilsStyle,
+) -> (MultiStyled<String>, Vec<&'a FsEntry>) {
+    // Icons are only shown before the first name of the chain
+    let inner = Display {
+        icons: false,
+        ..config.display.clone()
+    };
+
+    let mut name = display_name(entry, style, &config.display);
+    let mut chain = vec![entry];
+    while let Some(next) = chain.last().and_then(|e| only_child_dir(e, config)) {
+        if !config.display.suffix {
+            name.push("/".to_string().stylize());
+        }
+        name.append(display_name(next, style, &inner));
+        chain.push(next);
+    }
+
+    (name, chain)
+}
+
+fn only_child_dir<'a>(entry: &'a FsEntry, config: &Config) -> Option<&'a FsEntry> {
+    let children = entry.children.as_ref()?;
+    let mut remaining = children
+        .iter()
+        .filter(|c| !(config.display.prune && is_prunable(c)));
+
+    match (remaining.next(), remaining.next()) {
+        (Some(child), None) if child.e_type == EntryType::Directory => Some(child),
+        _ => None,
+    }
+}
+
+// A directory is pruned when nothing but other prunable directories were found in it. Directories
+// past the depth limit weren't read, so there's no telling and they are kept.
+fn is_prunable(entry: &FsEntry) -> bool {
+    match entry.children.as_ref() {
+        Some(children) => children.iter().all(|c| is_prunable(c)),
+        None => false,
+    }
+}
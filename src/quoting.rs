@@ -0,0 +1,174 @@
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// How file names are quoted and escaped, mirroring GNU ls' `--quoting-style`
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuotingStyle {
+    #[default]
+    Literal,
+    Shell,
+    ShellEscape,
+    C,
+    Escape,
+}
+
+const SHELL_SPECIAL: &str = " \t\n!\"#$&'()*;<=>?[\\]^`{|}~";
+
+#[derive(Clone, Copy)]
+enum Unit {
+    Char(char),
+    // A byte which isn't part of valid UTF-8
+    Byte(u8),
+}
+
+impl Unit {
+    fn printable(&self) -> bool {
+        match self {
+            Self::Char(c) => !c.is_control(),
+            Self::Byte(_) => false,
+        }
+    }
+
+    // C-style escape sequence, with octal for anything without a short form
+    fn escaped(&self) -> String {
+        match self {
+            Self::Char('\x07') => "\\a".into(),
+            Self::Char('\x08') => "\\b".into(),
+            Self::Char('\x0c') => "\\f".into(),
+            Self::Char('\n') => "\\n".into(),
+            Self::Char('\r') => "\\r".into(),
+            Self::Char('\t') => "\\t".into(),
+            Self::Char('\x0b') => "\\v".into(),
+            Self::Char(c) => {
+                let mut buf = [0; 4];
+                c.encode_utf8(&mut buf)
+                    .bytes()
+                    .map(|b| format!("\\{b:03o}"))
+                    .collect()
+            }
+            Self::Byte(b) => format!("\\{b:03o}"),
+        }
+    }
+}
+
+fn units(name: &OsStr) -> Vec<Unit> {
+    let mut units = Vec::new();
+    for chunk in name.as_bytes().utf8_chunks() {
+        units.extend(chunk.valid().chars().map(Unit::Char));
+        units.extend(chunk.invalid().iter().copied().map(Unit::Byte));
+    }
+
+    units
+}
+
+/// Renders a file name with the given quoting style. Control characters and invalid UTF-8 are
+/// never written raw to a terminal, regardless of style.
+pub fn quote(name: &OsStr, style: QuotingStyle, tty: bool) -> String {
+    let units = units(name);
+
+    match style {
+        QuotingStyle::Literal => literal(&units, tty),
+        QuotingStyle::Shell => shell(&units),
+        QuotingStyle::ShellEscape => shell_escape(&units),
+        QuotingStyle::C => format!("\"{}\"", escape(&units, "\"\\")),
+        QuotingStyle::Escape => escape(&units, " \\"),
+    }
+}
+
+fn literal(units: &[Unit], tty: bool) -> String {
+    if !tty {
+        return units
+            .iter()
+            .map(|u| match u {
+                Unit::Char(c) => *c,
+                Unit::Byte(_) => char::REPLACEMENT_CHARACTER,
+            })
+            .collect();
+    }
+
+    units
+        .iter()
+        .map(|u| match u {
+            Unit::Char(c) if u.printable() => *c,
+            _ => '?',
+        })
+        .collect()
+}
+
+// Backslash escapes nonprintable units and any of the characters in `special`
+fn escape(units: &[Unit], special: &str) -> String {
+    units
+        .iter()
+        .map(|u| match u {
+            Unit::Char(c) if special.contains(*c) => format!("\\{c}"),
+            Unit::Char(c) if u.printable() => c.to_string(),
+            _ => u.escaped(),
+        })
+        .collect()
+}
+
+fn needs_shell_quotes(units: &[Unit]) -> bool {
+    units.is_empty()
+        || units.iter().any(|u| match u {
+            Unit::Char(c) => SHELL_SPECIAL.contains(*c),
+            Unit::Byte(_) => false,
+        })
+}
+
+fn single_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+fn shell(units: &[Unit]) -> String {
+    let name: String = units
+        .iter()
+        .map(|u| match u {
+            Unit::Char(c) if u.printable() => *c,
+            _ => '?',
+        })
+        .collect();
+
+    if needs_shell_quotes(units) {
+        single_quote(&name)
+    } else {
+        name
+    }
+}
+
+fn shell_escape(units: &[Unit]) -> String {
+    if units.iter().all(Unit::printable) {
+        return shell(units);
+    }
+
+    // Alternate between '...' runs of printable characters and $'...' runs of escapes
+    let mut output = String::new();
+    let mut i = 0;
+    while i < units.len() {
+        let printable = units[i].printable();
+        let run: Vec<Unit> = units[i..]
+            .iter()
+            .take_while(|u| u.printable() == printable)
+            .copied()
+            .collect();
+        i += run.len();
+
+        if printable {
+            let s: String = run
+                .iter()
+                .filter_map(|u| match u {
+                    Unit::Char(c) => Some(*c),
+                    Unit::Byte(_) => None,
+                })
+                .collect();
+            output.push_str(&single_quote(&s));
+        } else {
+            let s: String = run.iter().map(Unit::escaped).collect();
+            output.push_str(&format!("$'{s}'"));
+        }
+    }
+
+    output
+}
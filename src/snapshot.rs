@@ -0,0 +1,169 @@
+use std::{
+    ffi::OsString,
+    fs::{self, Permissions},
+    io,
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::PermissionsExt,
+    },
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{self, Duration},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    diff::type_name,
+    files::{EntryType, FileType, FsEntry, Skipped, Times},
+    util::unix_seconds,
+};
+
+// Bumped whenever the format changes in a way older versions can't read
+const VERSION: u32 = 1;
+
+/// A listing saved to a file, to be compared against the directory later
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    /// The directory which was listed
+    pub path: PathBuf,
+    /// When the snapshot was taken, in seconds since the Unix epoch
+    pub created: u64,
+    pub root: Record,
+}
+
+/// The metadata of one entry, and of its children if they were listed
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Record {
+    pub name: String,
+    /// The name as saved on disk, when it isn't valid UTF-8 and so `name` is lossy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_bytes: Option<Vec<u8>>,
+    #[serde(rename = "type")]
+    pub e_type: String,
+    /// Permission bits, in octal
+    pub mode: String,
+    pub size: u64,
+    /// Modification time, in seconds since the Unix epoch
+    pub modified: u64,
+    pub uid: u32,
+    pub gid: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<Record>>,
+}
+
+impl Snapshot {
+    pub fn new(root: &FsEntry) -> Self {
+        Self {
+            version: VERSION,
+            path: fs::canonicalize(&root.path).unwrap_or_else(|_| root.path.clone()),
+            created: unix_seconds(time::SystemTime::now()),
+            root: Record::from(root),
+        }
+    }
+
+    pub fn save(&self, file: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(file, json + "\n")
+    }
+
+    pub fn load(file: &Path) -> io::Result<Self> {
+        let snapshot: Self = serde_json::from_str(&fs::read_to_string(file)?)?;
+        if snapshot.version > VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} was saved by a newer version of lils (format {})",
+                    file.display(),
+                    snapshot.version
+                ),
+            ));
+        }
+
+        Ok(snapshot)
+    }
+
+    /// The saved listing as entries, so it can be compared and displayed like a live one
+    pub fn entry(&self) -> FsEntry {
+        self.root.entry(&self.path)
+    }
+}
+
+impl From<&FsEntry> for Record {
+    fn from(entry: &FsEntry) -> Self {
+        Self {
+            name: entry.name.clone(),
+            name_bytes: entry
+                .raw_name
+                .to_str()
+                .is_none()
+                .then(|| entry.raw_name.as_bytes().to_vec()),
+            e_type: type_name(entry.e_type).to_string(),
+            mode: format!("{:04o}", entry.perms.mode() & 0o7777),
+            size: entry.size,
+            modified: unix_seconds(entry.times.modified),
+            uid: entry.uid,
+            gid: entry.gid,
+            user: entry.u_name.clone(),
+            group: entry.g_name.clone(),
+            children: entry
+                .children
+                .as_ref()
+                .map(|c| c.iter().map(|e| Record::from(e.as_ref())).collect()),
+        }
+    }
+}
+
+impl Record {
+    fn raw_name(&self) -> OsString {
+        match &self.name_bytes {
+            Some(bytes) => OsString::from_vec(bytes.clone()),
+            None => OsString::from(&self.name),
+        }
+    }
+
+    fn entry(&self, path: &Path) -> FsEntry {
+        let mode = u32::from_str_radix(&self.mode, 8).unwrap_or(0);
+        let e_type = match self.e_type.as_str() {
+            "directory" => EntryType::Directory,
+            "block device" => EntryType::BlockDevice,
+            "char device" => EntryType::CharDevice,
+            "symlink" => EntryType::Symlink,
+            "socket" => EntryType::Socket,
+            "fifo" => EntryType::Fifo,
+            _ if mode & 0o111 != 0 => EntryType::File(FileType::Executable),
+            _ => EntryType::File(FileType::from_extension(Path::new(&self.name))),
+        };
+        let modified = time::UNIX_EPOCH + Duration::from_secs(self.modified);
+
+        FsEntry {
+            name: self.name.clone(),
+            raw_name: self.raw_name(),
+            uid: self.uid,
+            u_name: self.user.clone(),
+            gid: self.gid,
+            g_name: self.group.clone(),
+            perms: Permissions::from_mode(mode),
+            path: path.to_path_buf(),
+            e_type,
+            times: Times {
+                modified,
+                created: modified,
+                access: modified,
+            },
+            size: self.size,
+            mount_point: false,
+            children: self.children.as_ref().map(|c| {
+                c.iter()
+                    .map(|r| Rc::new(r.entry(&path.join(r.raw_name()))))
+                    .collect()
+            }),
+            skipped: Skipped::default(),
+        }
+    }
+}
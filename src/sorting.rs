@@ -1,9 +1,9 @@
-use std::{fs, os::unix::fs::MetadataExt, path::Path, rc::Rc};
+use std::{cmp::Reverse, fs, os::unix::fs::MetadataExt, path::Path, rc::Rc};
 
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 
-use crate::files::{EntryChildren, FsEntry};
+use crate::files::{EntryChildren, EntryType, FsEntry};
 
 #[derive(Clone, Copy, ValueEnum, Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -32,40 +32,43 @@ pub fn sort(files: &EntryChildren, mode: SortingMode, reverse: bool) -> Vec<Rc<F
 
 fn time_sort(files: &EntryChildren) -> EntryChildren {
     let mut output = files.clone();
-    output.sort_by(|a, b| a.times.modified.cmp(&b.times.modified));
+    output.sort_by_key(|a| a.times.modified);
     output
 }
 
 fn name_sort(files: &EntryChildren) -> EntryChildren {
     let mut output = files.clone();
-    output.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    output.sort_by_key(|a| a.name.to_lowercase());
     output
 }
 
 fn size_sort(files: &EntryChildren) -> EntryChildren {
     let mut output = files.clone();
-    output.sort_by(|a, b| get_file_size(&b.path).cmp(&get_file_size(&a.path)));
+    output.sort_by_key(|a| Reverse(get_file_size(a)));
     output
 }
 
-fn get_file_size<P: AsRef<Path>>(path: &P) -> u64 {
-    let path = path.as_ref();
-    if path.is_dir() {
-        get_dir_size(path)
+// Entries the walk couldn't follow, like broken symlinks, already have the size of the link itself
+fn get_file_size(entry: &FsEntry) -> u64 {
+    if entry.e_type == EntryType::Directory {
+        get_dir_size(&entry.path)
     } else {
-        fs::metadata(path).unwrap().size()
+        entry.size
     }
 }
 
+// Anything which can't be read while adding up a directory counts as empty
 fn get_dir_size(path: &Path) -> u64 {
+    let Ok(read_dir) = fs::read_dir(path) else {
+        return 0;
+    };
     let mut size = 0;
-    let read_dir = fs::read_dir(path).unwrap();
 
     for entry in read_dir.flatten() {
-        if entry.file_type().unwrap().is_dir() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
             size += get_dir_size(&entry.path())
         } else {
-            size += entry.metadata().unwrap().size()
+            size += entry.metadata().map_or(0, |m| m.size())
         }
     }
 
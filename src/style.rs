@@ -1,37 +1,49 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, env};
 
+use clap::ValueEnum;
 use crossterm::style::{ContentStyle, StyledContent, Stylize};
+use serde::{Deserialize, Serialize};
 
 use crate::files::{EntryType, FileType, FsEntry};
 
-#[derive(Default, Debug, Clone)]
-pub struct PermissionStyle {
-    pub f_type: ContentStyle,
-    pub owner: ContentStyle,
-    pub group: ContentStyle,
-    pub other: ContentStyle,
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
 }
 
-impl PermissionStyle {
-    pub const fn f_type(mut self, style: ContentStyle) -> Self {
-        self.f_type = style;
-        self
-    }
-
-    pub const fn owner(mut self, style: ContentStyle) -> Self {
-        self.owner = style;
-        self
+impl ColorMode {
+    /// Reads the NO_COLOR and CLICOLOR_FORCE conventions, with NO_COLOR taking priority
+    pub fn from_env() -> Option<Self> {
+        let set = |var: &str| env::var_os(var).is_some_and(|v| !v.is_empty() && v != "0");
+
+        if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            Some(Self::Never)
+        } else if set("CLICOLOR_FORCE") {
+            Some(Self::Always)
+        } else {
+            None
+        }
     }
 
-    pub const fn group(mut self, style: ContentStyle) -> Self {
-        self.group = style;
-        self
+    pub fn enabled(self, tty: bool) -> bool {
+        match self {
+            Self::Auto => tty,
+            Self::Always => true,
+            Self::Never => false,
+        }
     }
+}
 
-    pub const fn other(mut self, style: ContentStyle) -> Self {
-        self.other = style;
-        self
-    }
+#[derive(Default, Debug, Clone)]
+pub struct PermissionStyle {
+    pub f_type: ContentStyle,
+    pub owner: ContentStyle,
+    pub group: ContentStyle,
+    pub other: ContentStyle,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -64,17 +76,7 @@ impl LilsStyle {
         self
     }
 
-    pub fn with_perms(mut self, perms: PermissionStyle) -> Self {
-        self.permissions = perms;
-        self
-    }
-
-    pub fn permissions_mut(&mut self) -> &mut PermissionStyle {
-        &mut self.permissions
-    }
-
-    pub fn apply(&self, file: &FsEntry) -> StyledContent<String> {
-        let name = file.name.clone();
+    pub fn apply(&self, file: &FsEntry, name: String) -> StyledContent<String> {
         match file.e_type {
             EntryType::Directory => self.directory.apply(name),
             EntryType::File(f_type) => {
@@ -85,6 +87,7 @@ impl LilsStyle {
             EntryType::BlockDevice => name.stylize(),
             EntryType::CharDevice => name.stylize(),
             EntryType::Socket => self.socket.apply(name),
+            EntryType::Fifo => name.stylize(),
         }
     }
 }
@@ -93,5 +96,9 @@ pub fn ls_style() -> LilsStyle {
     LilsStyle::default()
         .directory(ContentStyle::default().blue().bold())
         .socket(ContentStyle::default().green())
+        .symlink(ContentStyle::default().cyan())
         .set_ft(FileType::Executable, ContentStyle::default().red().bold())
+        .set_ft(FileType::Image, ContentStyle::default().magenta())
+        .set_ft(FileType::Audio, ContentStyle::default().cyan())
+        .set_ft(FileType::Video, ContentStyle::default().magenta().bold())
 }
@@ -1,6 +1,13 @@
-use std::{ffi::CStr, mem, ptr};
+use std::{
+    fs,
+    os::unix::ffi::OsStrExt,
+    panic,
+    path::Path,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use nix::unistd::{Gid, Uid};
+use nix::unistd::{Gid, Uid, gethostname};
 
 // I know unsafe code sucks, but libc will be libc
 pub fn get_user_by_uid(uid: u32) -> Option<String> {
@@ -17,3 +24,74 @@ pub fn get_group_by_gid(gid: u32) -> Option<String> {
         .flatten()
         .map(|g| g.name)
 }
+
+// file:// URL for a path, as used by OSC 8 hyperlinks
+pub fn file_url(path: &Path) -> Option<String> {
+    let path = fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .ok()?;
+    let host = gethostname().ok()?.to_string_lossy().to_string();
+
+    let mut encoded = String::new();
+    for b in path.as_os_str().as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(*b as char)
+            }
+            _ => encoded.push_str(&format!("%{b:02X}")),
+        }
+    }
+
+    Some(format!("file://{host}{encoded}"))
+}
+
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[0])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+pub fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Maps `f` over the items across all available cores, keeping their order
+pub fn par_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    if items.is_empty() {
+        return Vec::new();
+    }
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = items.len().div_ceil(threads);
+    let f = &f;
+
+    thread::scope(|s| {
+        let handles: Vec<_> = items
+            .chunks(chunk)
+            .map(|chunk| s.spawn(move || chunk.iter().map(f).collect::<Vec<R>>()))
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    })
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    os::fd::AsFd,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor::MoveTo,
+    execute,
+    terminal::{Clear, ClearType},
+};
+use nix::{
+    errno::Errno,
+    poll::{PollFd, PollFlags, PollTimeout, poll},
+    sys::inotify::{AddWatchFlags, InitFlags, Inotify, WatchDescriptor},
+};
+
+use crate::{config::Config, files::FsEntry, output::print};
+
+// How long a created or modified entry stays highlighted
+const HIGHLIGHT: Duration = Duration::from_secs(3);
+// Changes tend to come in bursts, like a whole checkout, so they are redrawn together
+const SETTLE: Duration = Duration::from_millis(50);
+
+/// Keeps the listing on screen, redrawing it whenever the listed directories change
+pub fn watch(paths: &[PathBuf], mut config: Config) -> io::Result<()> {
+    let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+    let mut watched: HashMap<WatchDescriptor, PathBuf> = HashMap::new();
+    let mut changed: HashMap<PathBuf, Instant> = HashMap::new();
+
+    loop {
+        changed.retain(|_, at| at.elapsed() < HIGHLIGHT);
+        config.display.highlighted = changed.keys().cloned().collect();
+
+        let (roots, _) = FsEntry::from_paths(paths, &config);
+        for dir in watched_dirs(&roots) {
+            if !watched.values().any(|p| *p == dir) {
+                // The directory may have been removed or replaced since it was listed, in which
+                // case the event for that will trigger another redraw anyway
+                match inotify.add_watch(&dir, watch_flags()) {
+                    Ok(wd) => {
+                        watched.insert(wd, dir);
+                    }
+                    Err(Errno::ENOENT | Errno::ENOTDIR) => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+
+        let mut stdout = io::stdout();
+        execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+        print(&roots, &config);
+        stdout.flush()?;
+
+        // Wake up when the oldest highlight expires, otherwise only for the next change
+        let timeout = changed
+            .values()
+            .map(|at| HIGHLIGHT.saturating_sub(at.elapsed()))
+            .min()
+            .map(|d| PollTimeout::try_from(d).unwrap_or(PollTimeout::MAX))
+            .unwrap_or(PollTimeout::NONE);
+        let mut fds = [PollFd::new(inotify.as_fd(), PollFlags::POLLIN)];
+        match poll(&mut fds, timeout) {
+            Ok(0) | Err(Errno::EINTR) => continue,
+            Ok(_) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        thread::sleep(SETTLE);
+        let now = Instant::now();
+        loop {
+            let events = match inotify.read_events() {
+                Ok(events) => events,
+                Err(Errno::EAGAIN) => break,
+                Err(e) => return Err(e.into()),
+            };
+
+            for event in events {
+                // The kernel drops the watch itself when the directory is removed
+                if event.mask.contains(AddWatchFlags::IN_IGNORED) {
+                    watched.remove(&event.wd);
+                    continue;
+                }
+                if event.mask.contains(AddWatchFlags::IN_DELETE)
+                    || event.mask.contains(AddWatchFlags::IN_MOVED_FROM)
+                {
+                    continue;
+                }
+                if let (Some(dir), Some(name)) = (watched.get(&event.wd), event.name) {
+                    changed.insert(dir.join(name), now);
+                }
+            }
+        }
+    }
+}
+
+fn watch_flags() -> AddWatchFlags {
+    AddWatchFlags::IN_CREATE
+        | AddWatchFlags::IN_DELETE
+        | AddWatchFlags::IN_MODIFY
+        | AddWatchFlags::IN_ATTRIB
+        | AddWatchFlags::IN_MOVED_FROM
+        | AddWatchFlags::IN_MOVED_TO
+        | AddWatchFlags::IN_ONLYDIR
+}
+
+// Every directory whose contents are part of the listing
+fn watched_dirs(roots: &[FsEntry]) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for root in roots.iter().filter(|r| r.children.is_some()) {
+        dirs.push(root.path.clone());
+        dirs.extend(root.get_all_dirs().iter().map(|d| d.path.clone()));
+    }
+
+    dirs
+}